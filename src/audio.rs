@@ -0,0 +1,299 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::core::Machine;
+use crate::error::Result;
+use crate::instructions::InstructionParser;
+use crate::timers::TIMER_FREQUENCY;
+
+pub const PATTERN_SIZE: usize = 16;
+pub const DEFAULT_PITCH: u8 = 64;
+// Samples per second played at the default pitch.
+pub const BASE_PLAYBACK_RATE: f64 = 4000.0;
+// Output is 16-bit mono at CD rate, a whole number of samples per frame.
+pub const SAMPLE_RATE: u32 = 44_100;
+pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / TIMER_FREQUENCY) as usize;
+const AMPLITUDE: i16 = i16::MAX / 4;
+const PATTERN_BITS: f64 = (PATTERN_SIZE * 8) as f64;
+const WAV_HEADER_SIZE: u32 = 44;
+
+// A square wave, the buzzer heard until a program loads a pattern of its own.
+const DEFAULT_PATTERN: [u8; PATTERN_SIZE] = [
+    0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00,
+];
+
+/**
+ * The XO-CHIP sound generator.
+ * While the sound timer runs the 128 bit pattern is played back one bit per
+ * sample, most significant bit first, looping. The pitch register sets the
+ * playback rate: 4000 samples per second at pitch 64, doubling every 48 steps.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioPattern {
+    pattern: [u8; PATTERN_SIZE],
+    pitch: u8,
+}
+
+impl Default for AudioPattern {
+    fn default() -> Self {
+        Self {
+            pattern: DEFAULT_PATTERN,
+            pitch: DEFAULT_PITCH,
+        }
+    }
+}
+
+impl AudioPattern {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pattern(&self) -> &[u8; PATTERN_SIZE] {
+        &self.pattern
+    }
+
+    pub fn set_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) {
+        self.pattern = pattern;
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+    }
+
+    pub fn playback_rate(&self) -> f64 {
+        BASE_PLAYBACK_RATE * 2f64.powf((f64::from(self.pitch) - 64.0) / 48.0)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// Somewhere for the buzzer to go, fed 16-bit mono samples at `SAMPLE_RATE`.
+pub trait AudioSink {
+    fn write(&mut self, samples: &[i16]) -> Result<()>;
+
+    // Called once nothing more will be written.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Throws everything away, for when nobody is listening.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn write(&mut self, _samples: &[i16]) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Keeps every sample, handy for checking what would have been heard.
+impl AudioSink for Vec<i16> {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        self.extend_from_slice(samples);
+        Ok(())
+    }
+}
+
+/**
+ * Writes a PCM WAV file.
+ * The header is written up front with empty sizes, which `finish` fills in
+ * once the length is known.
+*/
+pub struct WavSink<W: Write + Seek> {
+    writer: W,
+    data_size: u32,
+    finished: bool,
+}
+
+impl WavSink<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Seek> WavSink<W> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        let block_align: u16 = 2;
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(WAV_HEADER_SIZE - 8).to_le_bytes())?;
+        writer.write_all(b"WAVEfmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?; // format chunk size
+        writer.write_all(&1u16.to_le_bytes())?; // PCM
+        writer.write_all(&1u16.to_le_bytes())?; // mono
+        writer.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        writer.write_all(&(SAMPLE_RATE * u32::from(block_align)).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&16u16.to_le_bytes())?; // bits per sample
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            data_size: 0,
+            finished: false,
+        })
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavSink<W> {
+    fn write(&mut self, samples: &[i16]) -> Result<()> {
+        let mut data = Vec::with_capacity(samples.len() * 2);
+        for sample in samples {
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        self.writer.write_all(&data)?;
+        #[allow(clippy::cast_possible_truncation)]
+        let size = data.len() as u32;
+        self.data_size = self.data_size.saturating_add(size);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        self.writer.seek(SeekFrom::Start(4))?;
+        let riff_size = self.data_size.saturating_add(WAV_HEADER_SIZE - 8);
+        self.writer.write_all(&riff_size.to_le_bytes())?;
+        self.writer
+            .seek(SeekFrom::Start(u64::from(WAV_HEADER_SIZE) - 4))?;
+        self.writer.write_all(&self.data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Drop for WavSink<W> {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            error!("Unable to finish the WAV file: {}", e);
+        }
+    }
+}
+
+/**
+ * Turns the sound timer into samples, one frame at a time.
+ * While the buzzer is on the machine's pattern is played at its pitch; on
+ * anything but XO-CHIP that is the default square wave. Every beep starts
+ * from the top of the pattern.
+*/
+#[derive(Debug, Default, Clone)]
+pub struct Buzzer {
+    position: f64, // in pattern bits
+}
+
+impl Buzzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Call once per 60 Hz frame, after the timers ticked.
+    pub fn play<T, S>(&mut self, machine: &Machine<T>, sink: &mut S) -> Result<()>
+    where
+        T: InstructionParser,
+        S: AudioSink + ?Sized,
+    {
+        let samples = self.frame(machine.timers().buzzed(), machine.audio());
+        sink.write(&samples)
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn frame(&mut self, on: bool, audio: &AudioPattern) -> Vec<i16> {
+        if !on {
+            self.position = 0.0;
+            return vec![0; SAMPLES_PER_FRAME];
+        }
+        let step = audio.playback_rate() / f64::from(SAMPLE_RATE);
+        (0..SAMPLES_PER_FRAME)
+            .map(|_| {
+                let bit = self.position as usize;
+                self.position = (self.position + step) % PATTERN_BITS;
+                if audio.pattern()[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                    AMPLITUDE
+                } else {
+                    -AMPLITUDE
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_rate() {
+        let mut audio = AudioPattern::new();
+        assert!((audio.playback_rate() - 4000.0).abs() < 1e-9);
+        audio.set_pitch(112);
+        assert!((audio.playback_rate() - 8000.0).abs() < 1e-9);
+        audio.set_pitch(16);
+        assert!((audio.playback_rate() - 2000.0).abs() < 1e-9);
+    }
+
+    // Frames in which the buzzer could be heard.
+    fn beeps(samples: &[i16]) -> Vec<bool> {
+        samples
+            .chunks(SAMPLES_PER_FRAME)
+            .map(|frame| frame.iter().any(|s| *s != 0))
+            .collect()
+    }
+
+    #[test]
+    fn test_square_wave() {
+        let mut buzzer = Buzzer::new();
+        let samples = buzzer.frame(true, &AudioPattern::new());
+        assert_eq!(samples.len(), SAMPLES_PER_FRAME);
+        // 16 bits high at 4000 bits per second is 176.4 samples at 44.1 kHz
+        assert!(samples[..176].iter().all(|s| *s == AMPLITUDE));
+        assert!(samples[177..352].iter().all(|s| *s == -AMPLITUDE));
+        assert_eq!(
+            buzzer.frame(false, &AudioPattern::new()),
+            vec![0; SAMPLES_PER_FRAME]
+        );
+    }
+
+    #[test]
+    fn test_beep_timing() {
+        let mut machine = Machine::builder("TestVM").build().unwrap();
+        // LD V0, 3; LD ST, V0; JP 0x204
+        machine
+            .load_rom_bytes(&[0x60, 0x03, 0xF0, 0x18, 0x12, 0x04])
+            .unwrap();
+        let mut buzzer = Buzzer::new();
+        let mut samples = Vec::new();
+        for _ in 0..5 {
+            machine.run_frame().unwrap();
+            buzzer.play(&machine, &mut samples).unwrap();
+        }
+        assert_eq!(samples.len(), 5 * SAMPLES_PER_FRAME);
+        assert_eq!(beeps(&samples), vec![true, true, true, false, false]);
+    }
+
+    #[test]
+    fn test_wav() {
+        let mut wav = Vec::new();
+        {
+            let mut sink = WavSink::new(std::io::Cursor::new(&mut wav)).unwrap();
+            sink.write(&[0, 1, -1]).unwrap();
+            sink.finish().unwrap();
+        }
+        assert_eq!(wav.len(), 44 + 6);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(wav[4..8], 42u32.to_le_bytes());
+        assert_eq!(wav[24..28], SAMPLE_RATE.to_le_bytes());
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(wav[40..44], 6u32.to_le_bytes());
+        assert_eq!(wav[44..], [0, 0, 1, 0, 0xFF, 0xFF]);
+    }
+}
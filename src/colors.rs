@@ -0,0 +1,140 @@
+use crate::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+pub const COLUMN_WIDTH: usize = 8;
+pub const ZONE_HEIGHT: usize = 4;
+const COLUMNS: usize = DISPLAY_WIDTH / COLUMN_WIDTH;
+
+// Foreground colours of the VP-590 colour board, selected by the low 3 bits.
+pub const FOREGROUND_PALETTE: [(u8, u8, u8); 8] = [
+    (0x00, 0x00, 0x00), // black
+    (0xFF, 0x00, 0x00), // red
+    (0x00, 0x00, 0xFF), // blue
+    (0xFF, 0x00, 0xFF), // violet
+    (0x00, 0xFF, 0x00), // green
+    (0xFF, 0xFF, 0x00), // yellow
+    (0x00, 0xFF, 0xFF), // aqua
+    (0xFF, 0xFF, 0xFF), // white
+];
+
+// Background colours in the order 02A0 steps through them.
+pub const BACKGROUND_PALETTE: [(u8, u8, u8); 4] = [
+    (0x00, 0x00, 0x80), // blue
+    (0x00, 0x00, 0x00), // black
+    (0x00, 0x80, 0x00), // green
+    (0x80, 0x00, 0x00), // red
+];
+
+const DEFAULT_FOREGROUND: u8 = 1;
+
+/**
+ * The CHIP-8X colour attribute layer.
+ * It sits on top of the monochrome framebuffer: lit pixels take the
+ * foreground colour of their cell, everything else shows the background
+ * colour. Cells are 8 pixels wide and one pixel high; Bxy0 colours them in
+ * zones of 8x4 pixels, Bxyn row by row.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorAttributes {
+    background: u8,
+    foreground: Vec<u8>, // one entry per cell, row by row
+}
+
+impl Default for ColorAttributes {
+    fn default() -> Self {
+        Self {
+            background: 0,
+            foreground: vec![DEFAULT_FOREGROUND; COLUMNS * DISPLAY_HEIGHT],
+        }
+    }
+}
+
+impl ColorAttributes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Index into BACKGROUND_PALETTE.
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    pub fn cycle_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUND_PALETTE.len() as u8;
+    }
+
+    // Index into FOREGROUND_PALETTE of the cell holding pixel (x, y).
+    pub fn foreground(&self, x: usize, y: usize) -> u8 {
+        let column = (x % DISPLAY_WIDTH) / COLUMN_WIDTH;
+        self.foreground[(y % DISPLAY_HEIGHT) * COLUMNS + column]
+    }
+
+    /**
+     * Bxy0: the low nibbles of `horizontal` and `vertical` are the first
+     * zone column and zone row, the high nibbles how many more follow.
+     */
+    pub fn set_zones(&mut self, horizontal: u8, vertical: u8, color: u8) {
+        let columns = Self::span(horizontal);
+        let rows = Self::span(vertical);
+        for zone_row in rows {
+            for row in zone_row * ZONE_HEIGHT..(zone_row + 1) * ZONE_HEIGHT {
+                for column in columns.clone() {
+                    self.set(column, row, color);
+                }
+            }
+        }
+    }
+
+    // Bxyn: colour `rows` pixel rows from (x, y) down in the column holding x.
+    pub fn set_rows(&mut self, x: u8, y: u8, rows: u8, color: u8) {
+        let column = usize::from(x) % DISPLAY_WIDTH / COLUMN_WIDTH;
+        for row in usize::from(y)..usize::from(y) + usize::from(rows) {
+            self.set(column, row, color);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn span(value: u8) -> std::ops::Range<usize> {
+        let start = usize::from(value & 0xF);
+        start..start + usize::from(value >> 4) + 1
+    }
+
+    // Cells outside the screen wrap around, like sprites do.
+    fn set(&mut self, column: usize, row: usize, color: u8) {
+        let index = (row % DISPLAY_HEIGHT) * COLUMNS + column % COLUMNS;
+        self.foreground[index] = color & 0x7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_background() {
+        let mut colors = ColorAttributes::new();
+        assert_eq!(colors.background(), 0);
+        for _ in 0..5 {
+            colors.cycle_background();
+        }
+        assert_eq!(colors.background(), 1);
+    }
+
+    #[test]
+    fn test_set_zones_and_rows() {
+        let mut colors = ColorAttributes::new();
+        // two zone columns starting at 1, one zone row starting at 2
+        colors.set_zones(0x11, 0x02, 4);
+        assert_eq!(colors.foreground(8, 8), 4);
+        assert_eq!(colors.foreground(23, 11), 4);
+        assert_eq!(colors.foreground(24, 8), DEFAULT_FOREGROUND);
+        assert_eq!(colors.foreground(8, 12), DEFAULT_FOREGROUND);
+
+        colors.set_rows(40, 3, 2, 0xF);
+        assert_eq!(colors.foreground(47, 3), 7);
+        assert_eq!(colors.foreground(40, 4), 7);
+        assert_eq!(colors.foreground(40, 5), DEFAULT_FOREGROUND);
+    }
+}
@@ -0,0 +1,382 @@
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::core::Machine;
+use crate::error::{Error, ErrorKind, Result};
+use crate::frame::{Frame, Palette};
+use crate::instructions::InstructionParser;
+use crate::timers::{SoundEdge, FRAME_DURATION};
+
+// Events the host has not picked up yet. Frames beyond this are dropped
+// rather than queued, anything else waits for room.
+const EVENT_BUFFER: usize = 16;
+
+// What the host can ask of a running machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Pause,
+    Resume,
+    Step, // a single instruction, only while paused
+    Reset,
+    Quit,
+    KeyDown(u8),
+    KeyUp(u8),
+    SetSpeed(u32), // instructions per frame
+}
+
+// What a running machine tells the host.
+#[derive(Debug)]
+pub enum Event {
+    FrameReady(Frame),
+    SoundOn,
+    SoundOff,
+    Halted,
+    Fault(Error),
+}
+
+/**
+ * A machine running in a thread of its own.
+ * Commands go in and events come out over channels, so the machine is never
+ * touched from two threads. After `Halted` or `Fault` the machine waits for
+ * a `Reset` or `Quit`. Dropping the handle quits the machine.
+*/
+pub struct MachineHandle {
+    commands: Sender<Command>,
+    events: Receiver<Event>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MachineHandle {
+    /**
+     * Start running `machine` in real time. Frames are captured with the
+     * default palette. `rom` is what the machine was loaded with, `Reset`
+     * loads it again.
+     */
+    pub fn spawn<T>(machine: Machine<T>, rom: Vec<u8>) -> Self
+    where
+        T: InstructionParser + Send + 'static,
+    {
+        let (commands, command_receiver) = mpsc::channel();
+        let (event_sender, events) = mpsc::sync_channel(EVENT_BUFFER);
+        let runner = Runner::new(machine, rom, command_receiver, event_sender);
+        let thread = thread::spawn(move || {
+            debug!(
+                "Inside the spawned thread: {:?}",
+                std::thread::current().id()
+            );
+            runner.run()
+        });
+        Self {
+            commands,
+            events,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn send(&self, command: Command) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| ErrorKind::MachineStopped.into())
+    }
+
+    // Receive with `recv`, `try_recv` or `recv_timeout` as suits the host.
+    pub fn events(&self) -> &Receiver<Event> {
+        &self.events
+    }
+
+    // Quit the machine and wait for its thread to end.
+    pub fn join(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.commands.send(Command::Quit);
+            // The machine may be waiting for room to send an event.
+            while self.events.recv().is_ok() {}
+            if let Err(panic) = thread.join() {
+                if !thread::panicking() {
+                    std::panic::resume_unwind(panic);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for MachineHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Runner<T: InstructionParser> {
+    machine: Machine<T>,
+    rom: Vec<u8>, // reloaded on reset
+    commands: Receiver<Command>,
+    events: SyncSender<Event>,
+    palette: Palette,
+    paused: bool,
+    stopped: bool,  // halted or faulted
+    sounding: bool, // as last reported to the host
+    next_frame: Instant,
+}
+
+impl<T: InstructionParser> Runner<T> {
+    fn new(
+        machine: Machine<T>,
+        rom: Vec<u8>,
+        commands: Receiver<Command>,
+        events: SyncSender<Event>,
+    ) -> Self {
+        Self {
+            machine,
+            rom,
+            commands,
+            events,
+            palette: Palette::default(),
+            paused: false,
+            stopped: false,
+            sounding: false,
+            next_frame: Instant::now(),
+        }
+    }
+
+    // Returns once the host quits or goes away.
+    fn run(mut self) {
+        loop {
+            // A machine that is not running has nothing to do until told.
+            let command = if self.paused || self.stopped {
+                match self.commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => return,
+                }
+            } else {
+                match self.commands.try_recv() {
+                    Ok(command) => Some(command),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            };
+            if let Some(command) = command {
+                if !self.handle(command) {
+                    return;
+                }
+                continue;
+            }
+            let result = self.machine.run_frame();
+            if !self.report(result) {
+                return;
+            }
+            self.next_frame += FRAME_DURATION;
+            let now = Instant::now();
+            if self.next_frame > now {
+                thread::sleep(self.next_frame - now);
+            } else {
+                // We fell behind, don't try to catch up with a burst of frames.
+                self.next_frame = now;
+            }
+        }
+    }
+
+    // Returns false when it is time to stop.
+    fn handle(&mut self, command: Command) -> bool {
+        match command {
+            Command::Pause => self.paused = true,
+            Command::Resume => {
+                self.paused = false;
+                self.next_frame = Instant::now();
+            }
+            Command::Step if self.paused && !self.stopped => {
+                let result = self.machine.step();
+                return self.report(result);
+            }
+            Command::Step => {}
+            Command::Reset => {
+                let result = self
+                    .machine
+                    .reset()
+                    .and_then(|()| self.machine.load_rom_bytes(&self.rom))
+                    .map(|_| ());
+                self.stopped = false;
+                self.next_frame = Instant::now();
+                return self.report(result);
+            }
+            Command::Quit => return false,
+            Command::KeyDown(key) => self.machine.press_key(key),
+            Command::KeyUp(key) => self.machine.release_key(key),
+            Command::SetSpeed(cycles) => self.machine.set_cycles_per_frame(cycles),
+        }
+        true
+    }
+
+    // Tell the host how things went. Returns false if the host is gone.
+    fn report(&mut self, result: Result<()>) -> bool {
+        let sounding = match self.machine.sound_edge() {
+            Some(SoundEdge::Started) => true,
+            Some(SoundEdge::Stopped) => false,
+            None => self.machine.timers().sound_active(),
+        };
+        if sounding != self.sounding {
+            self.sounding = sounding;
+            let event = if sounding {
+                Event::SoundOn
+            } else {
+                Event::SoundOff
+            };
+            if self.events.send(event).is_err() {
+                return false;
+            }
+        }
+        let frame = Frame::capture(&self.machine, &self.palette);
+        match self.events.try_send(Event::FrameReady(frame)) {
+            Ok(()) | Err(TrySendError::Full(_)) => {}
+            Err(TrySendError::Disconnected(_)) => return false,
+        }
+        let event = match result {
+            Err(error) => Event::Fault(error),
+            Ok(()) if self.machine.is_halted() => Event::Halted,
+            Ok(()) => return true,
+        };
+        self.stopped = true;
+        self.events.send(event).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcodes::OpcodeMaskParser;
+    use crate::platform::Platform;
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::Duration;
+
+    // Only a bound for when something is broken, nothing waits this long.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn machine(platform: Platform, rom: &[u8]) -> Machine<OpcodeMaskParser> {
+        let mut machine = Machine::builder("TestVM")
+            .platform(platform)
+            .build()
+            .unwrap();
+        machine.load_rom_bytes(rom).unwrap();
+        machine
+    }
+
+    fn spawn(platform: Platform, rom: &[u8]) -> MachineHandle {
+        MachineHandle::spawn(machine(platform, rom), rom.to_vec())
+    }
+
+    // A runner driven from the test itself instead of its own thread.
+    fn runner(
+        platform: Platform,
+        rom: &[u8],
+    ) -> (Runner<OpcodeMaskParser>, Receiver<Event>, Sender<Command>) {
+        let (commands, command_receiver) = mpsc::channel();
+        let (event_sender, events) = mpsc::sync_channel(EVENT_BUFFER);
+        let runner = Runner::new(
+            machine(platform, rom),
+            rom.to_vec(),
+            command_receiver,
+            event_sender,
+        );
+        (runner, events, commands)
+    }
+
+    // The next event that is not a frame.
+    fn next_event(handle: &MachineHandle) -> Event {
+        loop {
+            match handle.events().recv_timeout(TIMEOUT).unwrap() {
+                Event::FrameReady(_) => {}
+                event => return event,
+            }
+        }
+    }
+
+    #[test]
+    fn test_sound_and_halt() {
+        // LD V0, 2; LD ST, V0; LD V1, 0; ADD V1, 1; SE V1, 0; JP 0x206; EXIT
+        let handle = spawn(
+            Platform::SuperChip,
+            &[
+                0x60, 0x02, 0xF0, 0x18, 0x61, 0x00, 0x71, 0x01, 0x31, 0x00, 0x12, 0x06, 0x00, 0xFD,
+            ],
+        );
+        assert!(matches!(next_event(&handle), Event::SoundOn));
+        assert!(matches!(next_event(&handle), Event::SoundOff));
+        assert!(matches!(next_event(&handle), Event::Halted));
+        // a halted machine still takes commands
+        handle.send(Command::Reset).unwrap();
+        assert!(matches!(next_event(&handle), Event::SoundOn));
+        handle.join();
+    }
+
+    #[test]
+    fn test_pause_step_and_keys() {
+        // LD V0, K; LD I, 0x50; DRW V0, V0, 5; JP 0x206
+        let (mut runner, events, _commands) = runner(
+            Platform::Chip8,
+            &[0xF0, 0x0A, 0xA0, 0x50, 0xD0, 0x05, 0x12, 0x06],
+        );
+        // after the first frame the machine is waiting for a key
+        let result = runner.machine.run_frame();
+        assert!(runner.report(result));
+        assert!(runner.handle(Command::Pause));
+        assert!(runner.handle(Command::KeyDown(0x4)));
+        assert!(runner.handle(Command::KeyUp(0x4)));
+        // the release completes Fx0A, then two steps draw the sprite at (4, 4)
+        assert!(runner.handle(Command::Step));
+        assert!(runner.handle(Command::Step));
+        let frames: Vec<Frame> = events
+            .try_iter()
+            .map(|event| match event {
+                Event::FrameReady(frame) => frame,
+                event => panic!("unexpected {:?}", event),
+            })
+            .collect();
+        assert_eq!(frames.len(), 3);
+        let palette = Palette::default();
+        assert_eq!(frames[1].pixel(4, 4), palette.colors[0]);
+        assert_eq!(frames[2].pixel(4, 4), palette.colors[1]);
+        assert_eq!(frames[2].pixel(3, 4), palette.colors[0]);
+
+        // stepping is for paused machines only
+        assert!(runner.handle(Command::Resume));
+        assert!(runner.handle(Command::Step));
+        assert!(events.try_recv().is_err());
+        assert!(!runner.handle(Command::Quit));
+    }
+
+    #[test]
+    fn test_reset_reloads_rom() {
+        // LD I, 0x208; LD V0, 0xAA; LD [I], V0; JP 0x206, then a zero word
+        let rom = [0xA2, 0x08, 0x60, 0xAA, 0xF0, 0x55, 0x12, 0x06, 0x00, 0x00];
+        let (mut runner, _events, _commands) = runner(Platform::Chip8, &rom);
+        runner.machine.run_frame().unwrap();
+        assert_eq!(runner.machine.memory()[0x208], 0xAA);
+        assert!(runner.handle(Command::Reset));
+        // the program is back as it was loaded, zero word included
+        assert_eq!(runner.machine.memory()[0x200..0x20A], rom);
+        assert_eq!(runner.machine.pc(), 0x200);
+        assert_eq!(runner.machine.registers()[0], 0);
+    }
+
+    #[test]
+    fn test_fault() {
+        let handle = spawn(Platform::Chip8, &[0xFF, 0xFF]);
+        match next_event(&handle) {
+            Event::Fault(error) => assert!(error.to_string().contains("invalid opcode")),
+            event => panic!("unexpected {:?}", event),
+        }
+        handle.send(Command::Quit).unwrap();
+        // the events channel closes once the thread is gone
+        loop {
+            match handle.events().recv_timeout(TIMEOUT) {
+                Ok(_) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => panic!("the machine did not quit"),
+            }
+        }
+        assert!(handle.send(Command::Resume).is_err());
+    }
+}
@@ -1,18 +1,45 @@
 use rand::Rng;
 use std::fmt;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+use std::thread;
+use std::time::Instant;
 
-use crate::instructions::{Instruction, InstructionParser};
+use crate::audio::{AudioPattern, PATTERN_SIZE};
+use crate::bitmasks::mask_0F00;
+use crate::colors::ColorAttributes;
+use crate::display::{Display, DISPLAY_HEIGHT, DISPLAY_WIDTH, PLANE_COUNT, VIP_HIRES_HEIGHT};
+use crate::error::{Error, ErrorKind, Result};
+use crate::font::{Font, FONT_ADDRESS_LIMIT};
+use crate::frame::{Frame, Palette};
+use crate::instructions::{Instruction, InstructionParser, InstructionSet};
+use crate::keypad::Keypad;
+use crate::megachip::{BlendMode, MegaDisplay, Sample, SAMPLE_HEADER_SIZE};
+use crate::opcodes::OpcodeMaskParser;
+use crate::platform::Platform;
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::screenshot::{self, ScreenshotOptions};
+use crate::timers::{SoundEdge, Timers, FRAME_DURATION};
 
-const MEMORY_SIZE: usize = 4096;
-const STACK_SIZE: usize = 16;
+// Fixed, instructions only have a nibble to name a register with.
 const REGISTER_COUNT: usize = 16;
 const PROGRAM_OFFSET: usize = 512;
+// The stack pointer is a byte.
+const MAX_STACK_SIZE: usize = 255;
+// The 32 MiB of MegaChip, the most any platform has.
+const MAX_MEMORY_SIZE: usize = 0x200_0000;
+// VIP hi-res ROMs start with a jump into the patched interpreter at 0x260,
+// which switches the display to 64x64 and runs the program from 0x2C0.
+const VIP_HIRES_MARKER: [u8; 2] = [0x12, 0x60];
+const VIP_HIRES_START: u16 = 0x2C0;
 const FLAG_REGISTER: usize = 15;
+const RPL_COUNT: usize = 8; // SUPER-CHIP 1.1 has flags for V0 to V7
+const SCROLL_COLUMNS: usize = 4;
 
 struct Memory {
-    mem: [u8; MEMORY_SIZE],
+    mem: Vec<u8>,
 }
 
 impl fmt::Debug for Memory {
@@ -28,18 +55,139 @@ impl fmt::Debug for Memory {
     }
 }
 
+/**
+ * What the machine does when a program calls too deep or returns from an
+ * empty stack.
+ * `Trap` hands the fault to the trap handler and carries on with the next
+ * instruction; without a handler installed it behaves like `Halt`.
+ * `Wrap` lets the stack pointer wrap around, overwriting the oldest return
+ * address, like some of the original interpreters did.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackPolicy {
+    Halt,
+    Trap,
+    Wrap,
+}
+
+/**
+ * How memory accesses relative to I behave when they run past the end of
+ * memory.
+ * `Wrap` wraps around the end of memory, so addresses are 12 bits wide
+ * like on the original interpreter, or 16 bits for XO-CHIP.
+ * `Fault` halts with an error, including when Fx1E moves I out of memory.
+ * `AmigaOverflow` wraps, but Fx1E also sets VF when I overflows past 0xFFF,
+ * which a few games written for the Amiga interpreter rely on.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryPolicy {
+    Wrap,
+    Fault,
+    AmigaOverflow,
+}
+
+/**
+ * The size of the machine: how much memory it has, how many return addresses
+ * the stack holds and where programs are loaded and start.
+ * The defaults come from the `Platform`, but real machines varied, e.g. VIPs
+ * shipped with 2 KiB of memory. The register file is always V0 to VF.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineConfig {
+    pub memory_size: usize,
+    pub stack_size: usize,
+    pub program_offset: u16,
+}
+
+impl Default for MachineConfig {
+    fn default() -> Self {
+        Platform::default().config()
+    }
+}
+
+impl MachineConfig {
+    /**
+     * Check the configuration can hold the font and at least one
+     * instruction of a program, and that memory is no larger than MegaChip's.
+     * The font also has to fit below the 16-bit address limit.
+     */
+    pub fn validate(&self, font: &Font) -> Result<()> {
+        let font_end = font.end();
+        let program_end = usize::from(self.program_offset) + 2;
+        let invalid = |reason: String| Err(ErrorKind::InvalidConfig(reason).into());
+        if self.memory_size > MAX_MEMORY_SIZE {
+            return invalid(format!(
+                "memory size {:#X} is larger than {:#X}",
+                self.memory_size, MAX_MEMORY_SIZE
+            ));
+        }
+        if font_end > FONT_ADDRESS_LIMIT {
+            return invalid(format!(
+                "the font at {:#X} runs past the 16-bit address space",
+                font.offset()
+            ));
+        }
+        if self.memory_size < font_end.max(program_end) {
+            return invalid(format!(
+                "memory size {:#X} leaves no room for the font and a program at {:#X}",
+                self.memory_size, self.program_offset
+            ));
+        }
+        if self.stack_size == 0 || self.stack_size > MAX_STACK_SIZE {
+            return invalid(format!(
+                "stack size {} is not between 1 and {}",
+                self.stack_size, MAX_STACK_SIZE
+            ));
+        }
+        Ok(())
+    }
+}
+
+// What ended up where after loading a ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadSummary {
+    pub bytes_loaded: usize,
+    pub start_address: u16,
+    pub end_address: usize, // one past the last byte of the ROM, may be 0x10000 or more
+}
+
+pub type TrapHandler = Box<dyn FnMut(&Error) + Send>;
+
 pub struct Machine<T: InstructionParser> {
     name: String,
     counter: u16,
+    load_address: u16, // where ROMs are loaded and execution starts
     stack_ptr: u8,
     mem: Memory,
-    stack: [u16; STACK_SIZE],
-    v: [u8; REGISTER_COUNT], // registers: v0 to vf
-    i: u16,                  // "There is also a 16-bit register called I."
-    delay_register: u8,
-    sound_register: u8,
+    stack: Vec<u16>,
+    stack_policy: StackPolicy,
+    trap_handler: Option<TrapHandler>,
+    memory_policy: MemoryPolicy,
+    protect_interpreter: bool, // make memory below the load address read-only
+    v: [u8; REGISTER_COUNT],   // registers: v0 to vf
+    i: u32, // "There is also a 16-bit register called I.", MegaChip widens it to 24 bits
+    timers: Timers,
+    cycles_per_frame: u32,
+    display: Display,
+    screen_height: usize, // height of the display outside of any hi-res mode
+    frame_drawn: bool,    // a sprite was drawn during the current frame
+    font: Font,
+    keypad: Keypad,
+    second_keypad: Keypad,       // CHIP-8X supports a second player
+    waiting_for_key: Option<u8>, // register waiting for the result of Fx0A
+    waiting_for_delay: bool,     // CHIP-8E waits for the delay timer to run out
+    quirks: Quirks,
     instruction_parser: T,
     skip_increment: bool,
+    halted: bool,         // set by the SUPER-CHIP exit instruction
+    rpl: [u8; RPL_COUNT], // SUPER-CHIP user flags, kept across resets
+    audio: AudioPattern,
+    colors: ColorAttributes,
+    output_port: u8, // last value written by the CHIP-8E Fx03
+    vip_hires: bool,
+    mega_mode: bool, // MegaChip programs draw on mega_display while this is set
+    mega_display: MegaDisplay,
+    sample: Option<Sample>,
 }
 
 impl<T> fmt::Debug for Machine<T>
@@ -47,7 +195,7 @@ where
     T: InstructionParser,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {{ \n\tPC: {}, \n\tSP: {}, \n\tStack: {:?}, \n\tRegisters: {:?}, \n\ti: {}, \n\tDR: {}, \n\tSR: {}, \n\tSKIP: {} }}", self.name, self.counter, self.stack_ptr, self.stack, self.v, self.i, self.delay_register, self.sound_register, self.skip_increment)
+        write!(f, "{} {{ \n\tPC: {}, \n\tSP: {}, \n\tStack: {:?}, \n\tRegisters: {:?}, \n\ti: {}, \n\tDR: {}, \n\tSR: {}, \n\tSKIP: {} }}", self.name, self.counter, self.stack_ptr, self.stack, self.v, self.i, self.timers.delay(), self.timers.sound(), self.skip_increment)
     }
 }
 
@@ -55,52 +203,237 @@ impl<T> Machine<T>
 where
     T: InstructionParser,
 {
-    pub fn new(name: &str, ins_parser: T) -> Self {
-        Self {
+    // A machine for the platform `ins_parser` decodes, with its defaults.
+    pub fn new(name: &str, ins_parser: T, quirks: Quirks) -> Result<Self> {
+        let platform = Platform::from(ins_parser.instruction_set());
+        Self::with_config(name, ins_parser, quirks, platform, platform.config())
+    }
+
+    /**
+     * Set up the display for `platform` and size the rest of the machine
+     * according to `config`, which is validated first.
+     */
+    fn with_config(
+        name: &str,
+        ins_parser: T,
+        quirks: Quirks,
+        platform: Platform,
+        config: MachineConfig,
+    ) -> Result<Self> {
+        let font = Font::default();
+        config.validate(&font)?;
+        let (_, height) = platform.display_size();
+        let vip_hires = platform == Platform::VipHires;
+        let mut machine = Self {
             name: name.to_string(),
-            counter: 512,
+            counter: config.program_offset,
+            load_address: config.program_offset,
             stack_ptr: 0,
             mem: Memory {
-                mem: [0; MEMORY_SIZE],
+                mem: vec![0; config.memory_size],
             },
-            stack: [0; STACK_SIZE],
+            stack: vec![0; config.stack_size],
+            stack_policy: StackPolicy::Halt,
+            trap_handler: None,
+            memory_policy: MemoryPolicy::Fault,
+            protect_interpreter: false,
             v: [0; REGISTER_COUNT],
             i: 0,
-            delay_register: 0,
-            sound_register: 0,
+            timers: Timers::new(),
+            cycles_per_frame: platform.cycles_per_frame(),
+            display: Display::new(),
+            screen_height: if vip_hires { DISPLAY_HEIGHT } else { height },
+            frame_drawn: false,
+            font,
+            keypad: Keypad::new(),
+            second_keypad: Keypad::new(),
+            waiting_for_key: None,
+            waiting_for_delay: false,
+            quirks,
             instruction_parser: ins_parser,
             skip_increment: false,
+            halted: false,
+            rpl: [0; RPL_COUNT],
+            audio: AudioPattern::new(),
+            colors: ColorAttributes::new(),
+            output_port: 0,
+            vip_hires,
+            mega_mode: false,
+            mega_display: MegaDisplay::new(),
+            sample: None,
+        };
+        machine.display.set_wrap(quirks.sprites_wrap);
+        machine.reset_display();
+        machine.counter = machine.start_address();
+        machine.load_font();
+        Ok(machine)
+    }
+
+    // The memory size, stack depth and load address the machine runs with.
+    pub fn config(&self) -> MachineConfig {
+        MachineConfig {
+            memory_size: self.mem.mem.len(),
+            stack_size: self.stack.len(),
+            program_offset: self.load_address,
         }
     }
 
-    pub fn load_rom(&mut self, filename: &str) -> Result<(), std::io::Error> {
-        let mut file = File::open(filename)?;
-        self._copy_into_mem(&mut file)?;
-        debug!("{:?}", self.mem);
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    /**
+     * Replace the built-in font, e.g. to match the glyphs or location used
+     * by a particular interpreter. The old glyphs are wiped from memory.
+     */
+    pub fn set_font(&mut self, font: Font) -> Result<()> {
+        self.config().validate(&font)?;
+        self.mem_range(usize::from(font.offset()), font.glyphs().len())?;
+        self.mem_range(usize::from(font.large_offset()), font.large_glyphs().len())?;
+        for (offset, len) in [
+            (self.font.offset(), self.font.glyphs().len()),
+            (self.font.large_offset(), self.font.large_glyphs().len()),
+        ]
+        .iter()
+        {
+            let start = usize::from(*offset);
+            for byte in self.mem.mem[start..start + len].iter_mut() {
+                *byte = 0;
+            }
+        }
+        self.font = font;
+        self.load_font();
         Ok(())
     }
 
-    fn _copy_into_mem(&mut self, file: &mut File) -> Result<(), std::io::Error> {
-        const BUFSIZE: usize = MEMORY_SIZE - PROGRAM_OFFSET;
-        let mut buffer: [u8; BUFSIZE] = [0; BUFSIZE];
+    // Copy the small and large font glyphs into the interpreter area of memory.
+    fn load_font(&mut self) {
+        let start = usize::from(self.font.offset());
+        let glyphs = self.font.glyphs();
+        self.mem.mem[start..start + glyphs.len()].copy_from_slice(glyphs);
+        let start = usize::from(self.font.large_offset());
+        let glyphs = self.font.large_glyphs();
+        self.mem.mem[start..start + glyphs.len()].copy_from_slice(glyphs);
+    }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        self.display.set_wrap(quirks.sprites_wrap);
+    }
 
-        // load the ROM into the buffer
-        let _ = file.read(&mut buffer)?;
+    pub fn load_address(&self) -> u16 {
+        self.load_address
+    }
 
-        // Copy the buffer into the VM memory
-        // TODO: Why not copy directly without the intermediate buffer
-        self.mem.mem[PROGRAM_OFFSET..].clone_from_slice(&buffer);
+    /**
+     * Change where ROMs are loaded, e.g. 0x600 for ETI-660 programs.
+     * Execution starts at the load address, now and after every reset.
+     */
+    pub fn set_load_address(&mut self, address: u16) -> Result<()> {
+        if usize::from(address) >= self.mem.mem.len() {
+            return Err(ErrorKind::MemoryOutOfBounds(usize::from(address)).into());
+        }
+        self.load_address = address;
+        self.counter = address;
         Ok(())
     }
 
+    pub fn is_vip_hires(&self) -> bool {
+        self.vip_hires
+    }
+
+    /**
+     * Switch to the 64x64 display of the VIP hi-res interpreter, or back.
+     * Execution starts at 0x2C0 instead of the load address in this mode,
+     * skipping the interpreter patch at the start of the ROM. Loading a ROM
+     * that starts with 1260 at 0x200 selects it automatically.
+     */
+    pub fn set_vip_hires(&mut self, enabled: bool) {
+        self.vip_hires = enabled;
+        self.reset_display();
+        self.counter = self.start_address();
+    }
+
+    // Where execution starts, now and after a reset.
+    fn start_address(&self) -> u16 {
+        if self.vip_hires {
+            VIP_HIRES_START
+        } else {
+            self.load_address
+        }
+    }
+
+    fn reset_display(&mut self) {
+        if self.vip_hires {
+            self.display.set_resolution(DISPLAY_WIDTH, VIP_HIRES_HEIGHT);
+        } else {
+            self.display
+                .set_resolution(DISPLAY_WIDTH, self.screen_height);
+        }
+    }
+
+    pub fn load_rom(&mut self, filename: &str) -> Result<LoadSummary> {
+        let file = File::open(filename)?;
+        self._copy_into_mem(&mut BufReader::new(file))
+    }
+
+    pub fn load_rom_from<R: Read>(&mut self, reader: &mut R) -> Result<LoadSummary> {
+        self._copy_into_mem(reader)
+    }
+
+    /**
+     * Copy a ROM image to the load address.
+     * The rest of program memory is cleared so nothing is left over from a
+     * previously loaded ROM. Images that do not fit are rejected as a whole.
+     */
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<LoadSummary> {
+        let start = usize::from(self.load_address);
+        let capacity = self.mem.mem.len() - start;
+        if rom.len() > capacity {
+            return Err(ErrorKind::RomTooLarge {
+                size: rom.len(),
+                capacity,
+            }
+            .into());
+        }
+        for byte in self.mem.mem[start..].iter_mut() {
+            *byte = 0;
+        }
+        self.mem.mem[start..start + rom.len()].copy_from_slice(rom);
+        debug!("{:?}", self.mem);
+        if start == PROGRAM_OFFSET
+            && rom.starts_with(&VIP_HIRES_MARKER)
+            && self.instruction_parser.instruction_set() == InstructionSet::Chip8
+        {
+            info!("Detected a VIP hi-res ROM, switching to 64x64");
+            self.set_vip_hires(true);
+        }
+        Ok(LoadSummary {
+            bytes_loaded: rom.len(),
+            start_address: self.load_address,
+            end_address: start + rom.len(),
+        })
+    }
+
+    fn _copy_into_mem<R: Read>(&mut self, reader: &mut R) -> Result<LoadSummary> {
+        // read_to_end keeps reading until EOF, a single read may return short
+        let mut rom = Vec::new();
+        reader.read_to_end(&mut rom)?;
+        self.load_rom_bytes(&rom)
+    }
+
     /**
-    * Create a 16-bit opcode out of 2 bytes
-    * Ref: <https://stackoverflow.com/a/50244328>
-    * Shift the bits by 8 to the left:
-        (XXXXXXXX becomes XXXXXXXX00000000)
-    * THEN bitwise-OR to concatenate them:
-    *   (XXXXXXXX00000000 | YYYYYYYY) = XXXXXXXXYYYYYYYY
-    **/
+     * Create a 16-bit opcode out of 2 bytes
+     * Ref: <https://stackoverflow.com/a/50244328>
+     * Shift the bits by 8 to the left:
+     *   (XXXXXXXX becomes XXXXXXXX00000000)
+     * THEN bitwise-OR to concatenate them:
+     *   (XXXXXXXX00000000 | YYYYYYYY) = XXXXXXXXYYYYYYYY
+     **/
     fn get_opcode(b: &[u8]) -> u16 {
         let mut fb = u16::from(b[0]);
         let sb = u16::from(b[1]);
@@ -108,38 +441,361 @@ where
         fb | sb
     }
 
+    // Range of `len` bytes of memory starting at `address`, if all of it exists.
+    fn mem_range(&self, address: usize, len: usize) -> Result<Range<usize>> {
+        let size = self.mem.mem.len();
+        if address + len > size {
+            return Err(ErrorKind::MemoryOutOfBounds(address.max(size)).into());
+        }
+        Ok(address..address + len)
+    }
+
+    pub fn memory_policy(&self) -> MemoryPolicy {
+        self.memory_policy
+    }
+
+    pub fn set_memory_policy(&mut self, policy: MemoryPolicy) {
+        self.memory_policy = policy;
+    }
+
+    // Reject writes to the interpreter area below the program, font included.
+    pub fn set_protect_interpreter(&mut self, protect: bool) {
+        self.protect_interpreter = protect;
+    }
+
+    // Map an address onto memory according to the memory policy.
+    fn resolve(&self, address: usize) -> Result<usize> {
+        match self.memory_policy {
+            MemoryPolicy::Fault if address >= self.mem.mem.len() => {
+                Err(ErrorKind::MemoryOutOfBounds(address).into())
+            }
+            MemoryPolicy::Fault => Ok(address),
+            MemoryPolicy::Wrap | MemoryPolicy::AmigaOverflow => Ok(address % self.mem.mem.len()),
+        }
+    }
+
+    fn read_mem(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        for (n, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.mem.mem[self.resolve(address + n)?];
+        }
+        Ok(())
+    }
+
+    // Every address is checked before anything is written, so a faulting
+    // write leaves memory untouched.
+    fn write_mem(&mut self, address: usize, data: &[u8]) -> Result<()> {
+        let mut addresses = Vec::with_capacity(data.len());
+        for n in 0..data.len() {
+            let resolved = self.resolve(address + n)?;
+            if self.protect_interpreter && resolved < usize::from(self.load_address) {
+                return Err(ErrorKind::WriteProtected(resolved).into());
+            }
+            addresses.push(resolved);
+        }
+        for (resolved, byte) in addresses.into_iter().zip(data) {
+            self.mem.mem[resolved] = *byte;
+        }
+        Ok(())
+    }
+
+    // Fx1E: I stays within the address space.
+    #[allow(clippy::cast_possible_truncation)]
+    fn add_i(&mut self, value: u8) -> Result<()> {
+        let res = self.index() + usize::from(value);
+        let size = self.mem.mem.len();
+        match self.memory_policy {
+            MemoryPolicy::Fault if res >= size => {
+                return Err(ErrorKind::MemoryOutOfBounds(res).into());
+            }
+            MemoryPolicy::AmigaOverflow => {
+                self.v[FLAG_REGISTER] = u8::from(res >= size);
+            }
+            _ => {}
+        }
+        self.i = (res % size) as u32;
+        Ok(())
+    }
+
+    pub fn stack_policy(&self) -> StackPolicy {
+        self.stack_policy
+    }
+
+    pub fn set_stack_policy(&mut self, policy: StackPolicy) {
+        self.stack_policy = policy;
+    }
+
+    // Called with stack faults when the stack policy is `Trap`.
+    pub fn set_trap_handler(&mut self, handler: TrapHandler) {
+        self.trap_handler = Some(handler);
+    }
+
+    /**
+     * The stack pointer always points at the next free slot, so the stack is
+     * empty at 0 and full at the depth of the stack.
+     */
+    fn push(&mut self, address: u16) -> Result<()> {
+        if usize::from(self.stack_ptr) == self.stack.len() {
+            match self.stack_policy {
+                StackPolicy::Wrap => self.stack_ptr = 0,
+                _ => return Err(ErrorKind::StackOverflow.into()),
+            }
+        }
+        self.stack[usize::from(self.stack_ptr)] = address;
+        self.stack_ptr += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u16> {
+        if self.stack_ptr == 0 {
+            match self.stack_policy {
+                #[allow(clippy::cast_possible_truncation)]
+                StackPolicy::Wrap => self.stack_ptr = self.stack.len() as u8,
+                _ => return Err(ErrorKind::StackUnderflow.into()),
+            }
+        }
+        self.stack_ptr -= 1;
+        Ok(self.stack[usize::from(self.stack_ptr)])
+    }
+
+    // Whether a fault should be handed to the trap handler instead of halting.
+    fn traps(&self, error: &Error) -> bool {
+        let stack_fault = matches!(
+            error.kind(),
+            ErrorKind::StackOverflow | ErrorKind::StackUnderflow
+        );
+        stack_fault && self.stack_policy == StackPolicy::Trap && self.trap_handler.is_some()
+    }
+
     fn inc_pc(&mut self) {
-        self.counter += 2;
+        self.counter = self.counter.wrapping_add(2);
+    }
+
+    // Skip the next instruction, which may be one of the four byte long loads,
+    // F000 nnnn for XO-CHIP or 01nn nnnn for MegaChip.
+    fn skip_next(&mut self) {
+        let next = usize::from(self.counter) + 2;
+        let long = match self.mem.mem.get(next..next + 2).map(Self::get_opcode) {
+            Some(opcode) => match self.instruction_parser.instruction_set() {
+                InstructionSet::XoChip => opcode == 0xF000,
+                InstructionSet::MegaChip => opcode & 0xFF00 == 0x0100,
+                _ => false,
+            },
+            None => false,
+        };
+        self.inc_pc();
+        if long {
+            self.inc_pc();
+        }
     }
 
     #[allow(clippy::cast_possible_truncation)]
     fn add(&mut self, d1: u8, d2: u8) -> u8 {
         let res: u16 = u16::from(d1) + u16::from(d2);
-        self.v[FLAG_REGISTER] = if res > u16::from(u8::max_value()) {
-            1
-        } else {
-            0
-        };
+        self.v[FLAG_REGISTER] = if res > u16::from(u8::MAX) { 1 } else { 0 };
         res as u8
     }
 
+    // XO-CHIP leaves I alone after 5xy2/5xy3, CHIP-8E moves it past the range.
     #[allow(clippy::cast_possible_truncation)]
-    fn add_16(&mut self, d1: u16, d2: u16) -> u16 {
-        let res: u32 = u32::from(d1) + u32::from(d2);
-        self.v[FLAG_REGISTER] = if res > u32::from(u16::max_value()) {
-            1
+    fn advance_i_past_range(&mut self, len: usize) {
+        if self.instruction_parser.instruction_set() == InstructionSet::Chip8E {
+            self.i = self.i.wrapping_add(len as u32);
+        }
+    }
+
+    // Registers Vx to Vy in that order, counting down when x > y.
+    fn register_range(x: u8, y: u8) -> impl ExactSizeIterator<Item = usize> {
+        let (x, y) = (usize::from(x), usize::from(y));
+        let (low, high) = (x.min(y), x.max(y));
+        let descending = x > y;
+        (low..high + 1).map(move |n| if descending { high + low - n } else { n })
+    }
+
+    // The original interpreter clobbered VF in 8xy1, 8xy2 and 8xy3.
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.v[FLAG_REGISTER] = 0;
+        }
+    }
+
+    fn shift_source(&self, reg1: u8, reg2: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            reg2
         } else {
-            0
+            reg1
+        }
+    }
+
+    // I as a memory address.
+    fn index(&self) -> usize {
+        self.i as usize
+    }
+
+    // Move I past the registers stored or loaded by Fx55 and Fx65.
+    #[allow(clippy::cast_possible_truncation)]
+    fn advance_i(&mut self, register: usize) {
+        let increment = match self.quirks.index_increment {
+            IndexIncrement::Unchanged => return,
+            IndexIncrement::ByX => register,
+            IndexIncrement::ByXPlusOne => register + 1,
         };
-        res as u16
+        self.i = self.i.wrapping_add(increment as u32);
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.counter
+    }
+
+    pub fn instruction_set(&self) -> InstructionSet {
+        self.instruction_parser.instruction_set()
+    }
+
+    pub fn registers(&self) -> &[u8; REGISTER_COUNT] {
+        &self.v
+    }
+
+    pub fn index_register(&self) -> u32 {
+        self.i
+    }
+
+    // The return addresses currently on the stack, oldest first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..usize::from(self.stack_ptr)]
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.mem.mem
+    }
+
+    // What the screen shows right now, in the colours of `palette`.
+    pub fn frame(&self, palette: &Palette) -> Frame {
+        Frame::capture(self, palette)
+    }
+
+    /**
+     * Save what the screen shows as a PNG, PBM or PGM image, whichever the
+     * extension of `path` asks for.
+     */
+    pub fn save_screenshot<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &ScreenshotOptions,
+    ) -> Result<()> {
+        screenshot::save(&self.frame(&options.palette), options.scale, path)
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.timers.tick();
+    }
+
+    // Whether the buzzer turned on or off since this was last called.
+    pub fn sound_edge(&mut self) -> Option<SoundEdge> {
+        self.timers.sound_edge()
+    }
+
+    pub fn cycles_per_frame(&self) -> u32 {
+        self.cycles_per_frame
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles: u32) {
+        self.cycles_per_frame = cycles;
+    }
+
+    pub fn keypad(&self) -> &Keypad {
+        &self.keypad
+    }
+
+    pub fn press_key(&mut self, key: u8) {
+        self.keypad.press(key);
+    }
+
+    /**
+     * Releasing a key is what completes a pending Fx0A, matching the
+     * original interpreter which waited for a full press and release.
+     */
+    pub fn release_key(&mut self, key: u8) {
+        let was_pressed = self.keypad.is_pressed(key);
+        self.keypad.release(key);
+        if was_pressed {
+            if let Some(register) = self.waiting_for_key.take() {
+                self.v[usize::from(register)] = key;
+            }
+        }
+    }
+
+    pub fn second_keypad(&self) -> &Keypad {
+        &self.second_keypad
+    }
+
+    // The second CHIP-8X keypad is only read by ExF2 and ExF5.
+    pub fn press_second_key(&mut self, key: u8) {
+        self.second_keypad.press(key);
+    }
+
+    pub fn release_second_key(&mut self, key: u8) {
+        self.second_keypad.release(key);
+    }
+
+    pub fn colors(&self) -> &ColorAttributes {
+        &self.colors
+    }
+
+    pub fn is_mega_mode(&self) -> bool {
+        self.mega_mode
+    }
+
+    pub fn mega_display(&self) -> &MegaDisplay {
+        &self.mega_display
+    }
+
+    // The MegaChip sample currently playing, if any.
+    pub fn sample(&self) -> Option<&Sample> {
+        self.sample.as_ref()
+    }
+
+    pub fn output_port(&self) -> u8 {
+        self.output_port
     }
 
-    fn execute(&mut self, ins: &Instruction) {
+    // Whether the machine can't execute instructions until something happens.
+    fn is_blocked(&self) -> bool {
+        self.is_waiting_for_key()
+            || self.halted
+            || (self.waiting_for_delay && self.timers.delay() > 0)
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key.is_some()
+    }
+
+    pub fn audio(&self) -> &AudioPattern {
+        &self.audio
+    }
+
+    // Whether the program ended itself with the SUPER-CHIP exit instruction.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    fn execute(&mut self, ins: &Instruction) -> Result<()> {
         match *ins {
-            Instruction::ClearScreen => {}
+            Instruction::ClearScreen => {
+                if self.mega_mode {
+                    self.mega_display.clear();
+                } else {
+                    self.display.clear();
+                }
+            }
             Instruction::Return => {
-                self.counter = self.stack[usize::from(self.stack_ptr)];
-                self.stack_ptr -= 1;
+                self.counter = self.pop()?;
                 self.skip_increment = true;
             }
             Instruction::SYS => {}
@@ -148,24 +804,25 @@ where
                 self.skip_increment = true;
             }
             Instruction::Call(address) => {
-                self.stack_ptr += 1;
-                self.stack[usize::from(self.stack_ptr)] = self.counter;
+                // Return to the instruction following the call, which wraps
+                // around like the PC does for a call in the last word.
+                self.push(self.counter.wrapping_add(2))?;
                 self.counter = address;
                 self.skip_increment = true;
             }
             Instruction::SkipEqualsByte(reg, byte) => {
                 if self.v[usize::from(reg)] == byte {
-                    self.inc_pc();
+                    self.skip_next();
                 }
             }
             Instruction::SkipNotEqualsByte(reg, byte) => {
                 if self.v[usize::from(reg)] != byte {
-                    self.inc_pc();
+                    self.skip_next();
                 }
             }
             Instruction::SkipEqualsRegister(reg1, reg2) => {
                 if self.v[usize::from(reg1)] == self.v[usize::from(reg2)] {
-                    self.inc_pc();
+                    self.skip_next();
                 }
             }
             Instruction::LoadByte(reg, byte) => {
@@ -179,118 +836,568 @@ where
             }
             Instruction::Or(reg1, reg2) => {
                 self.v[usize::from(reg1)] |= self.v[usize::from(reg2)];
+                self.reset_vf_after_logic();
             }
             Instruction::And(reg1, reg2) => {
                 self.v[usize::from(reg1)] &= self.v[usize::from(reg2)];
+                self.reset_vf_after_logic();
             }
             Instruction::Xor(reg1, reg2) => {
                 self.v[usize::from(reg1)] ^= self.v[usize::from(reg2)];
+                self.reset_vf_after_logic();
             }
             Instruction::AddRegister(reg1, reg2) => {
                 self.v[usize::from(reg1)] =
                     self.add(self.v[usize::from(reg1)], self.v[usize::from(reg2)]);
             }
+            Instruction::SubRegister(reg1, reg2) => {
+                let (res, borrow) =
+                    self.v[usize::from(reg1)].overflowing_sub(self.v[usize::from(reg2)]);
+                self.v[usize::from(reg1)] = res;
+                self.v[FLAG_REGISTER] = u8::from(!borrow);
+            }
+            Instruction::ShiftRight(reg1, reg2) => {
+                let value = self.v[usize::from(self.shift_source(reg1, reg2))];
+                self.v[usize::from(reg1)] = value >> 1;
+                self.v[FLAG_REGISTER] = value & 0x1;
+            }
+            Instruction::SubNRegister(reg1, reg2) => {
+                let (res, borrow) =
+                    self.v[usize::from(reg2)].overflowing_sub(self.v[usize::from(reg1)]);
+                self.v[usize::from(reg1)] = res;
+                self.v[FLAG_REGISTER] = u8::from(!borrow);
+            }
+            Instruction::ShiftLeft(reg1, reg2) => {
+                let value = self.v[usize::from(self.shift_source(reg1, reg2))];
+                self.v[usize::from(reg1)] = value << 1;
+                self.v[FLAG_REGISTER] = value >> 7;
+            }
+            Instruction::SkipNotEqualRegister(reg1, reg2) => {
+                if self.v[usize::from(reg1)] != self.v[usize::from(reg2)] {
+                    self.skip_next();
+                }
+            }
             Instruction::LoadImmediate(address) => {
-                self.i = address;
+                self.i = u32::from(address);
+            }
+            Instruction::JumpBase(address) => {
+                let register = if self.quirks.jump_uses_vx {
+                    usize::from(mask_0F00(address))
+                } else {
+                    0
+                };
+                self.counter = address + u16::from(self.v[register]);
+                self.skip_increment = true;
+            }
+            Instruction::DisplaySprite(reg1, reg2, _) if self.mega_mode => {
+                let mut sprite = vec![0; self.mega_display.sprite_size()];
+                self.read_mem(self.index(), &mut sprite)?;
+                let x = usize::from(self.v[usize::from(reg1)]);
+                let y = usize::from(self.v[usize::from(reg2)]);
+                let collision = self.mega_display.draw_sprite(x, y, &sprite);
+                self.frame_drawn = true;
+                self.v[FLAG_REGISTER] = u8::from(collision);
+            }
+            Instruction::DisplaySprite(reg1, reg2, rows) => {
+                // one set of rows for each selected XO-CHIP plane
+                let mut sprite = [0; 15 * PLANE_COUNT];
+                let sprite = &mut sprite[..usize::from(rows) * self.display.plane_count()];
+                self.read_mem(self.index(), sprite)?;
+                let x = usize::from(self.v[usize::from(reg1)]);
+                let y = usize::from(self.v[usize::from(reg2)]);
+                let collision = self.display.draw_sprite(x, y, sprite);
+                self.frame_drawn = true;
+                self.v[FLAG_REGISTER] = u8::from(collision);
             }
             Instruction::Random(register, data) => {
                 let random_byte = rand::thread_rng().gen_range(0, 255);
                 self.v[usize::from(register)] = random_byte & data;
             }
+            Instruction::SkipKeyPress(register) => {
+                if self.keypad.is_pressed(self.v[usize::from(register)] & 0xF) {
+                    self.skip_next();
+                }
+            }
+            Instruction::SkipNotKeyPress(register) => {
+                if !self.keypad.is_pressed(self.v[usize::from(register)] & 0xF) {
+                    self.skip_next();
+                }
+            }
+            Instruction::LoadKeyPress(register) => {
+                self.waiting_for_key = Some(register);
+            }
             Instruction::LoadFromDelay(register) => {
-                self.v[usize::from(register)] = self.delay_register;
+                self.v[usize::from(register)] = self.timers.delay();
             }
             Instruction::LoadDelay(register) => {
-                self.delay_register = register;
+                self.timers.set_delay(self.v[usize::from(register)]);
             }
             Instruction::LoadSound(register) => {
-                self.sound_register = register;
+                self.timers.set_sound(self.v[usize::from(register)]);
             }
             Instruction::AddI(register) => {
-                self.i = self.add_16(self.i, u16::from(self.v[usize::from(register)]));
+                self.add_i(self.v[usize::from(register)])?;
+            }
+            Instruction::LoadFontSprite(register) => {
+                self.i = u32::from(self.font.glyph_address(self.v[usize::from(register)]));
             }
             Instruction::LoadIBCD(register) => {
                 // Store BCD representation of Vx in memory locations I, I+1 and I+2.
-                self.mem.mem[usize::from(self.i)] = register / 100;
-                self.mem.mem[usize::from(self.i) + 1] = (register / 10) % 10;
-                self.mem.mem[usize::from(self.i) + 2] = register % 10;
+                let value = self.v[usize::from(register)];
+                self.write_mem(self.index(), &[value / 100, (value / 10) % 10, value % 10])?;
             }
             Instruction::StoreRegisters(register) => {
                 let register: usize = usize::from(register);
-                for n in 0..=register {
-                    self.mem.mem[usize::from(self.i) + n] = self.v[n];
-                }
+                let values = self.v;
+                self.write_mem(self.index(), &values[..=register])?;
+                self.advance_i(register);
                 trace!("{:?}", self.mem);
             }
             Instruction::LoadRegisters(register) => {
                 let register: usize = usize::from(register);
-                for n in 0..=register {
-                    self.v[n] = self.mem.mem[usize::from(self.i) + n]
-                }
+                let mut values = [0; REGISTER_COUNT];
+                self.read_mem(self.index(), &mut values[..=register])?;
+                self.v[..=register].copy_from_slice(&values[..=register]);
+                self.advance_i(register);
                 debug!("{:?}", self.mem);
             }
-            _ => unimplemented!(),
-        };
-        trace!("{:?}", self);
-    }
-
-    // Resets the machine back to the original state
-    pub fn reset(&mut self) -> Result<(), String> {
-        self.counter = 512;
-        self.stack_ptr = 0;
-        self.mem.mem = [0; MEMORY_SIZE];
-        self.stack = [0; STACK_SIZE];
-        self.v = [0; REGISTER_COUNT];
-        self.i = 0;
-        self.delay_register = 0;
-        self.sound_register = 0;
-        Ok(())
-    }
-
-    // Start the virtual machine: This is the fun part!
-    pub fn start(&mut self) -> Result<(), String> {
-        loop {
-            // we check for 4095 because we need to read 2 bytes.
-            if self.counter > 4095 {
-                return Err(String::from("PC out of bounds"));
-            }
-            let opcode = {
-                let pc: usize = usize::from(self.counter);
-                Self::get_opcode(&self.mem.mem[pc..=pc + 1])
-            };
-            if opcode != 0 {
-                trace!("PC: {}, opcode = {:X}", self.counter, opcode);
-            }
-            let instruction = self
-                .instruction_parser
-                .try_from(opcode)
-                .expect("Could not parse opcode");
-            trace!("Instruction: {:X?}", instruction);
-            self.execute(&instruction);
-            if !self.skip_increment {
+            Instruction::ScrollDown(rows) if self.mega_mode => {
+                self.mega_display.scroll(0, isize::from(rows));
+            }
+            Instruction::ScrollDown(rows) => {
+                self.display.scroll_down(usize::from(rows));
+            }
+            Instruction::ScrollRight if self.mega_mode => {
+                self.mega_display.scroll(SCROLL_COLUMNS as isize, 0);
+            }
+            Instruction::ScrollRight => {
+                self.display.scroll_right(SCROLL_COLUMNS);
+            }
+            Instruction::ScrollLeft if self.mega_mode => {
+                self.mega_display.scroll(-(SCROLL_COLUMNS as isize), 0);
+            }
+            Instruction::ScrollLeft => {
+                self.display.scroll_left(SCROLL_COLUMNS);
+            }
+            Instruction::Exit => {
+                self.halted = true;
+                self.skip_increment = true;
+            }
+            Instruction::LowResolution => {
+                // Back to the platform's own screen, e.g. 64x48 on the ETI-660.
+                self.reset_display();
+            }
+            Instruction::HighResolution => {
+                self.display.set_high_resolution(true);
+            }
+            Instruction::DisplayLargeSprite(reg1, reg2) => {
+                let mut sprite = [0; 32 * PLANE_COUNT];
+                let sprite = &mut sprite[..32 * self.display.plane_count()];
+                self.read_mem(self.index(), sprite)?;
+                let x = usize::from(self.v[usize::from(reg1)]);
+                let y = usize::from(self.v[usize::from(reg2)]);
+                let collision = self.display.draw_large_sprite(x, y, sprite);
+                self.frame_drawn = true;
+                self.v[FLAG_REGISTER] = u8::from(collision);
+            }
+            Instruction::LoadLargeFontSprite(register) => {
+                self.i = u32::from(self.font.large_glyph_address(self.v[usize::from(register)]));
+            }
+            Instruction::StoreFlags(register) => {
+                // Only V0 to V7 have a flag to go to.
+                let register = usize::from(register).min(RPL_COUNT - 1);
+                self.rpl[..=register].copy_from_slice(&self.v[..=register]);
+            }
+            Instruction::LoadFlags(register) => {
+                let register = usize::from(register).min(RPL_COUNT - 1);
+                self.v[..=register].copy_from_slice(&self.rpl[..=register]);
+            }
+            Instruction::ScrollUp(rows) if self.mega_mode => {
+                self.mega_display.scroll(0, -isize::from(rows));
+            }
+            Instruction::ScrollUp(rows) => {
+                self.display.scroll_up(usize::from(rows));
+            }
+            Instruction::SaveRange(reg1, reg2) => {
+                // Vx goes first even if x > y.
+                let values: Vec<u8> = Self::register_range(reg1, reg2)
+                    .map(|register| self.v[register])
+                    .collect();
+                self.write_mem(self.index(), &values)?;
+                self.advance_i_past_range(values.len());
+            }
+            Instruction::LoadRange(reg1, reg2) => {
+                let mut values = [0; REGISTER_COUNT];
+                let registers = Self::register_range(reg1, reg2);
+                let values = &mut values[..registers.len()];
+                self.read_mem(self.index(), values)?;
+                let len = values.len();
+                for (register, value) in registers.zip(values.iter()) {
+                    self.v[register] = *value;
+                }
+                self.advance_i_past_range(len);
+            }
+            Instruction::LoadLongImmediate => {
+                let mut address = [0; 2];
+                self.read_mem(usize::from(self.counter) + 2, &mut address)?;
+                self.i = u32::from(Self::get_opcode(&address));
                 self.inc_pc();
             }
-            self.skip_increment = false;
-        }
-    }
-}
-
-#[cfg(test)]
-use std::io::{Seek, SeekFrom, Write};
+            Instruction::SelectPlanes(planes) => {
+                self.display.select_planes(planes);
+            }
+            Instruction::LoadAudio => {
+                let mut pattern = [0; PATTERN_SIZE];
+                self.read_mem(self.index(), &mut pattern)?;
+                self.audio.set_pattern(pattern);
+            }
+            Instruction::LoadPitch(register) => {
+                self.audio.set_pitch(self.v[usize::from(register)]);
+            }
+            Instruction::CycleBackground => {
+                self.colors.cycle_background();
+            }
+            Instruction::AddNibbles(reg1, reg2) => {
+                // Each nibble is added on its own, modulo 8.
+                let (x, y) = (self.v[usize::from(reg1)], self.v[usize::from(reg2)]);
+                self.v[usize::from(reg1)] = ((x & 0x77) + (y & 0x77)) & 0x77;
+            }
+            Instruction::SetColorZones(reg1, reg2) => {
+                let horizontal = self.v[usize::from(reg1)];
+                let vertical = self.v[usize::from(reg1 + 1) % REGISTER_COUNT];
+                self.colors
+                    .set_zones(horizontal, vertical, self.v[usize::from(reg2)]);
+            }
+            Instruction::SetColorRows(reg1, reg2, rows) => {
+                let x = self.v[usize::from(reg1)];
+                let y = self.v[usize::from(reg1 + 1) % REGISTER_COUNT];
+                self.colors.set_rows(x, y, rows, self.v[usize::from(reg2)]);
+            }
+            Instruction::SkipSecondKeyPress(register) => {
+                if self
+                    .second_keypad
+                    .is_pressed(self.v[usize::from(register)] & 0xF)
+                {
+                    self.skip_next();
+                }
+            }
+            Instruction::SkipNotSecondKeyPress(register) => {
+                if !self
+                    .second_keypad
+                    .is_pressed(self.v[usize::from(register)] & 0xF)
+                {
+                    self.skip_next();
+                }
+            }
+            Instruction::WaitDelay => {
+                self.waiting_for_delay = true;
+            }
+            Instruction::Skip => {
+                self.skip_next();
+            }
+            Instruction::SkipGreater(reg1, reg2) => {
+                if self.v[usize::from(reg1)] > self.v[usize::from(reg2)] {
+                    self.skip_next();
+                }
+            }
+            // Relative jumps count from the next instruction, as the PC has
+            // already moved past BBnn/BFnn on the VIP.
+            Instruction::JumpBack(offset) => {
+                self.counter = self.counter.wrapping_add(2).wrapping_sub(u16::from(offset));
+                self.skip_increment = true;
+            }
+            Instruction::JumpForward(offset) => {
+                self.counter = self.counter.wrapping_add(2).wrapping_add(u16::from(offset));
+                self.skip_increment = true;
+            }
+            Instruction::OutputPort(register) => {
+                self.output_port = self.v[usize::from(register)];
+            }
+            Instruction::SkipBytes(register) => {
+                self.counter = self
+                    .counter
+                    .wrapping_add(u16::from(self.v[usize::from(register)]));
+            }
+            Instruction::LoadDelayAndWait(register) => {
+                self.timers.set_delay(self.v[usize::from(register)]);
+                self.waiting_for_delay = true;
+            }
+            Instruction::MegaOff => {
+                self.mega_mode = false;
+            }
+            Instruction::MegaOn => {
+                self.mega_mode = true;
+            }
+            Instruction::LoadMegaImmediate(high) => {
+                let mut low = [0; 2];
+                self.read_mem(usize::from(self.counter) + 2, &mut low)?;
+                self.i = u32::from(high) << 16 | u32::from(Self::get_opcode(&low));
+                self.inc_pc();
+            }
+            Instruction::LoadPalette(count) => {
+                let mut colors = vec![0; usize::from(count) * 4];
+                self.read_mem(self.index(), &mut colors)?;
+                self.mega_display.load_palette(&colors);
+            }
+            Instruction::SpriteWidth(width) => {
+                self.mega_display.set_sprite_width(width);
+            }
+            Instruction::SpriteHeight(height) => {
+                self.mega_display.set_sprite_height(height);
+            }
+            Instruction::ScreenAlpha(alpha) => {
+                self.mega_display.set_alpha(alpha);
+            }
+            Instruction::PlaySample(mode) => {
+                let mut header = [0; SAMPLE_HEADER_SIZE];
+                self.read_mem(self.index(), &mut header)?;
+                let (rate, len) = Sample::header(&header);
+                let mut data = vec![0; len];
+                self.read_mem(self.index() + SAMPLE_HEADER_SIZE, &mut data)?;
+                self.sample = Some(Sample {
+                    rate,
+                    data,
+                    looping: mode == 0,
+                });
+            }
+            Instruction::StopSample => {
+                self.sample = None;
+            }
+            Instruction::BlendMode(mode) => {
+                self.mega_display
+                    .set_blend_mode(BlendMode::from_nibble(mode));
+            }
+            Instruction::CollisionColor(index) => {
+                self.mega_display.set_collision_color(index);
+            }
+        };
+        trace!("{:?}", self);
+        Ok(())
+    }
+
+    // Resets the machine back to the original state
+    pub fn reset(&mut self) -> Result<()> {
+        self.counter = self.start_address();
+        self.stack_ptr = 0;
+        for byte in self.mem.mem.iter_mut() {
+            *byte = 0;
+        }
+        self.load_font();
+        for address in self.stack.iter_mut() {
+            *address = 0;
+        }
+        self.v = [0; REGISTER_COUNT];
+        self.i = 0;
+        self.timers.reset();
+        self.display.clear();
+        self.keypad.reset();
+        self.second_keypad.reset();
+        self.colors.reset();
+        self.waiting_for_key = None;
+        self.waiting_for_delay = false;
+        self.output_port = 0;
+        self.mega_mode = false;
+        self.mega_display.reset();
+        self.sample = None;
+        self.reset_display();
+        self.display.select_planes(1);
+        self.audio.reset();
+        self.halted = false;
+        Ok(())
+    }
+
+    // Fetch, decode and execute a single instruction.
+    // Does nothing while the machine is blocked waiting for a key.
+    pub fn step(&mut self) -> Result<()> {
+        if self.is_blocked() {
+            return Ok(());
+        }
+        self.waiting_for_delay = false;
+        let pc = self.counter;
+        // we need to be able to read 2 bytes at the PC.
+        if usize::from(pc) + 1 >= self.mem.mem.len() {
+            return Err(Error::new(ErrorKind::PcOutOfBounds).with_pc(pc));
+        }
+        let opcode = {
+            let pc: usize = usize::from(pc);
+            Self::get_opcode(&self.mem.mem[pc..=pc + 1])
+        };
+        if opcode != 0 {
+            trace!("PC: {}, opcode = {:X}", pc, opcode);
+        }
+        let instruction = self
+            .instruction_parser
+            .try_from(opcode)
+            .map_err(|e| e.with_pc(pc))?;
+        trace!("Instruction: {:X?}", instruction);
+        if let Err(error) = self.execute(&instruction) {
+            let error = error.with_pc(pc).with_opcode(opcode);
+            if !self.traps(&error) {
+                return Err(error);
+            }
+            warn!("Trapped: {}", error);
+            if let Some(handler) = self.trap_handler.as_mut() {
+                handler(&error);
+            }
+        }
+        if !self.skip_increment {
+            self.inc_pc();
+        }
+        self.skip_increment = false;
+        Ok(())
+    }
+
+    /**
+     * Run one 60 Hz frame: execute up to `cycles_per_frame` instructions and
+     * then tick the timers once. The timers keep running while the machine
+     * is blocked on a key press or waiting for the vertical blank.
+     */
+    pub fn run_frame(&mut self) -> Result<()> {
+        self.frame_drawn = false;
+        for _ in 0..self.cycles_per_frame {
+            if self.is_blocked() {
+                break;
+            }
+            // With the display wait quirk a sprite draw ends the frame.
+            if self.quirks.display_wait && self.frame_drawn {
+                break;
+            }
+            self.step()?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    // Start the virtual machine: This is the fun part!
+    // Returns once the program exits.
+    pub fn start(&mut self) -> Result<()> {
+        let mut next_frame = Instant::now();
+        loop {
+            self.run_frame()?;
+            if self.halted {
+                return Ok(());
+            }
+            next_frame += FRAME_DURATION;
+            let now = Instant::now();
+            if next_frame > now {
+                thread::sleep(next_frame - now);
+            } else {
+                // We fell behind, don't try to catch up with a burst of frames.
+                next_frame = now;
+            }
+        }
+    }
+}
+
+impl Machine<OpcodeMaskParser> {
+    pub fn builder(name: &str) -> MachineBuilder {
+        MachineBuilder::new(name)
+    }
+}
+
+/**
+ * Sets up a machine for a `Platform`.
+ * The platform picks the instruction set, memory size, stack depth, load
+ * address, display, quirks and clock speed; all but the instruction set and
+ * display can be overridden on top of that.
+*/
+#[derive(Debug, Clone)]
+pub struct MachineBuilder {
+    name: String,
+    platform: Platform,
+    quirks: Option<Quirks>,
+    cycles_per_frame: Option<u32>,
+    memory_size: Option<usize>,
+    stack_size: Option<usize>,
+    program_offset: Option<u16>,
+}
+
+impl MachineBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            platform: Platform::default(),
+            quirks: None,
+            cycles_per_frame: None,
+            memory_size: None,
+            stack_size: None,
+            program_offset: None,
+        }
+    }
+
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
 
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = Some(quirks);
+        self
+    }
+
+    pub fn cycles_per_frame(mut self, cycles: u32) -> Self {
+        self.cycles_per_frame = Some(cycles);
+        self
+    }
+
+    pub fn memory_size(mut self, size: usize) -> Self {
+        self.memory_size = Some(size);
+        self
+    }
+
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    pub fn program_offset(mut self, offset: u16) -> Self {
+        self.program_offset = Some(offset);
+        self
+    }
+
+    // Fails with `InvalidConfig` if the sizes don't make a usable machine.
+    pub fn build(self) -> Result<Machine<OpcodeMaskParser>> {
+        let parser = OpcodeMaskParser::new(self.platform.instruction_set());
+        self.build_with(parser)
+    }
+
+    // Use a parser of your own, it should decode the platform's instruction set.
+    pub fn build_with<T: InstructionParser>(self, parser: T) -> Result<Machine<T>> {
+        let defaults = self.platform.config();
+        let config = MachineConfig {
+            memory_size: self.memory_size.unwrap_or(defaults.memory_size),
+            stack_size: self.stack_size.unwrap_or(defaults.stack_size),
+            program_offset: self.program_offset.unwrap_or(defaults.program_offset),
+        };
+        let quirks = self.quirks.unwrap_or_else(|| self.platform.quirks());
+        let mut machine = Machine::with_config(&self.name, parser, quirks, self.platform, config)?;
+        machine.set_cycles_per_frame(
+            self.cycles_per_frame
+                .unwrap_or_else(|| self.platform.cycles_per_frame()),
+        );
+        Ok(machine)
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
-    use crate::opcodes::OpcodeMaskParser;
+    use crate::font::{FONT_OFFSET, LARGE_GLYPHS, STANDARD_GLYPHS, VIP_GLYPHS};
+    use crate::instructions::InstructionSet;
     use crate::opcodesv2::OpcodeTable;
+    use std::io::{Seek, SeekFrom, Write};
+
+    // How deep the stack of a plain CHIP-8 machine is.
+    const STACK_SIZE: usize = 16;
 
     #[test]
     fn test_copy_into_mem_no_data() {
         let mut tmpfile = tempfile::tempfile().unwrap();
-        let mut vm = Machine::new("TestVM", OpcodeTable {});
+        let mut vm = Machine::new("TestVM", OpcodeTable::default(), Quirks::default()).unwrap();
         vm._copy_into_mem(&mut tmpfile).unwrap();
         assert_eq!(vm.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in vm.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in vm.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
     }
@@ -298,18 +1405,73 @@ mod tests {
     #[test]
     fn test_copy_into_mem_some_data() {
         let mut tmpfile = tempfile::tempfile().unwrap();
-        let mut vm = Machine::new("TestVM", OpcodeTable {});
+        let mut vm = Machine::new("TestVM", OpcodeTable::default(), Quirks::default()).unwrap();
         write!(tmpfile, "Hello World!").unwrap(); // Write
         tmpfile.seek(SeekFrom::Start(0)).unwrap(); // Seek to start
         vm._copy_into_mem(&mut tmpfile).unwrap();
         let expected = [72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33];
-        let mut count = 0;
-        for _ in 0..expected.len() {
-            assert_eq!(vm.mem.mem[PROGRAM_OFFSET + count], expected[count]);
-            count += 1;
+        for (count, byte) in expected.iter().enumerate() {
+            assert_eq!(vm.mem.mem[PROGRAM_OFFSET + count], *byte);
         }
     }
 
+    #[test]
+    fn test_copy_into_mem_too_large() {
+        let mut vm = Machine::new("TestVM", OpcodeTable::default(), Quirks::default()).unwrap();
+        let rom = vec![0xAA; Platform::Chip8.memory_size() - PROGRAM_OFFSET];
+        let summary = vm._copy_into_mem(&mut &rom[..]).unwrap();
+        assert_eq!(summary.bytes_loaded, 3584);
+        assert_eq!(summary.end_address, 4096);
+
+        let rom = vec![0xBB; Platform::Chip8.memory_size() - PROGRAM_OFFSET + 1];
+        let error = vm._copy_into_mem(&mut &rom[..]).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::RomTooLarge {
+                size: 3585,
+                capacity: 3584
+            }
+        ));
+        // memory is left untouched
+        assert_eq!(vm.mem.mem[PROGRAM_OFFSET], 0xAA);
+    }
+
+    #[test]
+    fn test_load_rom_bytes() {
+        let mut vm = Machine::new("TestVM", OpcodeTable::default(), Quirks::default()).unwrap();
+        vm.load_rom_bytes(&[1, 2, 3, 4]).unwrap();
+        let summary = vm.load_rom_bytes(&[5, 6]).unwrap();
+        assert_eq!(
+            summary,
+            LoadSummary {
+                bytes_loaded: 2,
+                start_address: 0x200,
+                end_address: 0x202
+            }
+        );
+        // leftovers of the previous ROM are cleared
+        assert_eq!(vm.mem.mem[PROGRAM_OFFSET..PROGRAM_OFFSET + 4], [5, 6, 0, 0]);
+    }
+
+    #[test]
+    fn test_load_address() {
+        let mut vm = Machine::new("TestVM", OpcodeTable::default(), Quirks::default()).unwrap();
+        vm.set_load_address(0x600).unwrap();
+        assert_eq!(vm.counter, 0x600);
+        let summary = vm.load_rom_bytes(&[0x12, 0x34]).unwrap();
+        assert_eq!(summary.start_address, 0x600);
+        assert_eq!(summary.end_address, 0x602);
+        assert_eq!(vm.mem.mem[0x600..0x602], [0x12, 0x34]);
+        assert_eq!(vm.mem.mem[PROGRAM_OFFSET], 0);
+
+        vm.counter = 0x700;
+        vm.reset().unwrap();
+        assert_eq!(vm.counter, 0x600);
+
+        assert!(vm.set_load_address(0x1000).is_err());
+        assert_eq!(vm.load_address(), 0x600);
+    }
+
     #[test]
     fn test_create_opcode() {
         assert_eq!(Machine::<OpcodeTable>::get_opcode(&[0x31, 0x42]), 0x3142);
@@ -328,193 +1490,1564 @@ mod tests {
         // TODO: We might need a reset method to go back to the original state
         // Each instruction has a primary task and might also potentially have
         // some side-effect. We need to test both
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
-        machine.execute(&Instruction::ClearScreen);
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.execute(&Instruction::ClearScreen).unwrap();
         assert_eq!(machine.counter, 512);
         assert_eq!(machine.stack_ptr, 0);
 
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
 
         assert_eq!(machine.stack, [0; STACK_SIZE]);
         assert_eq!(machine.v, [0; REGISTER_COUNT]);
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
     }
 
     #[test]
     fn test_execute_ret() {
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
         // TODO: Should we artificially introduce modifications in the machine to test behaviour?
         // TODO: Perhaps a fixture-like ROM which is read before each test run.
         // Seems like it would be necessary otherwise a lot of behaviour can't be tested.
         // Modify the counter and the stack pointer before the machine execution starts
         machine.counter = 1;
         machine.stack_ptr = 1;
-        machine.execute(&Instruction::Return);
+        machine.execute(&Instruction::Return).unwrap();
         assert_eq!(machine.counter, 0);
         assert_eq!(machine.stack_ptr, 0);
-        assert_eq!(machine.skip_increment, true);
+        assert!(machine.skip_increment);
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
         assert_eq!(machine.stack, [0; STACK_SIZE]);
         assert_eq!(machine.v, [0; REGISTER_COUNT]);
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
     }
 
     #[test]
     fn test_execute_sys() {
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
-        machine.execute(&Instruction::SYS);
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.execute(&Instruction::SYS).unwrap();
         assert_eq!(machine.counter, 512);
         assert_eq!(machine.stack_ptr, 0);
-        assert_eq!(machine.skip_increment, false);
+        assert!(!machine.skip_increment);
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
         assert_eq!(machine.stack, [0; STACK_SIZE]);
         assert_eq!(machine.v, [0; REGISTER_COUNT]);
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
     }
 
     #[test]
     fn test_execute_jump() {
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
 
         assert_eq!(machine.counter, 512); // before machine executes instruction
 
-        machine.execute(&Instruction::Jump(0x0222));
+        machine.execute(&Instruction::Jump(0x0222)).unwrap();
         assert_eq!(machine.counter, 0x0222);
 
-        machine.execute(&Instruction::Jump(4095));
+        machine.execute(&Instruction::Jump(4095)).unwrap();
         assert_eq!(machine.counter, 4095);
 
         assert_eq!(machine.stack_ptr, 0);
-        assert_eq!(machine.skip_increment, true);
+        assert!(machine.skip_increment);
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
         assert_eq!(machine.stack, [0; STACK_SIZE]);
         assert_eq!(machine.v, [0; REGISTER_COUNT]);
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
     }
 
     #[test]
     fn test_execute_call() {
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
 
         assert_eq!(machine.counter, 512); // before machine executes instruction
         assert_eq!(machine.stack_ptr, 0);
 
         machine.counter = 25;
-        machine.execute(&Instruction::Call(0x0222));
+        machine.execute(&Instruction::Call(0x0222)).unwrap();
         assert_eq!(machine.stack_ptr, 1); // increments the stack pointer
         assert_eq!(machine.counter, 0x0222); // pushes the current pc to the stack
-        assert_eq!(machine.skip_increment, true); // we're gonna skip the next automatic pc increment
-        assert_eq!(machine.stack[usize::from(machine.stack_ptr)], 25); // stack has the old pc
+        assert!(machine.skip_increment); // we're gonna skip the next automatic pc increment
+        assert_eq!(machine.stack[0], 27); // stack has the address after the old pc
 
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
         assert_eq!(machine.v, [0; REGISTER_COUNT]);
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
     }
 
     #[test]
     fn test_execute_se() {
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
 
         assert_eq!(machine.counter, 512); // before machine executes instruction
-        machine.execute(&Instruction::SkipEqualsByte(machine.v[1], 0x0001)); // nothing should happen
+        machine
+            .execute(&Instruction::SkipEqualsByte(machine.v[1], 0x0001))
+            .unwrap(); // nothing should happen
         assert_eq!(machine.counter, 512);
 
         machine.v[1] = 0x0001;
-        machine.execute(&Instruction::SkipEqualsByte(machine.v[1], 0x0001)); // nothing should happen
+        machine
+            .execute(&Instruction::SkipEqualsByte(machine.v[1], 0x0001))
+            .unwrap(); // nothing should happen
         assert_eq!(machine.counter, 514);
 
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
     }
 
     #[test]
     fn test_execute_sne() {
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
 
         assert_eq!(machine.counter, 512); // before machine executes instruction
         machine.v[1] = 0x0001;
-        machine.execute(&Instruction::SkipNotEqualsByte(machine.v[1], 0x0001));
+        machine
+            .execute(&Instruction::SkipNotEqualsByte(machine.v[1], 0x0001))
+            .unwrap();
         assert_eq!(machine.counter, 512);
 
-        machine.reset();
+        machine.reset().unwrap();
         machine.v[1] = 0x0001;
 
-        machine.execute(&Instruction::SkipNotEqualsByte(machine.v[1], 0x0002));
+        machine
+            .execute(&Instruction::SkipNotEqualsByte(machine.v[1], 0x0002))
+            .unwrap();
         assert_eq!(machine.counter, 514);
 
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
     }
 
     #[test]
     fn test_execute_se_reg() {
-        let mut machine = Machine::new("TestVM", OpcodeMaskParser {});
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
 
         assert_eq!(machine.counter, 512); // before machine executes instruction
         machine.v[1] = 0x0001;
         machine.v[12] = 0x0001;
-        machine.execute(&Instruction::SkipEqualsRegister(
-            machine.v[1],
-            machine.v[12],
-        ));
+        machine
+            .execute(&Instruction::SkipEqualsRegister(
+                machine.v[1],
+                machine.v[12],
+            ))
+            .unwrap();
         assert_eq!(machine.counter, 514);
 
         machine.v[1] = 0x0002;
-        machine.execute(&Instruction::SkipEqualsRegister(
-            machine.v[1],
-            machine.v[12],
-        ));
+        machine
+            .execute(&Instruction::SkipEqualsRegister(
+                machine.v[1],
+                machine.v[12],
+            ))
+            .unwrap();
         assert_eq!(machine.counter, 514);
 
         assert_eq!(machine.mem.mem.len(), 4096);
-        // every byte in memory is zero when file is empty
-        for byte in machine.mem.mem.iter() {
+        // every byte of program memory is zero when file is empty
+        for byte in machine.mem.mem[PROGRAM_OFFSET..].iter() {
             assert_eq!(*byte, 0);
         }
         assert_eq!(machine.i, 0);
-        assert_eq!(machine.delay_register, 0);
-        assert_eq!(machine.sound_register, 0);
+        assert_eq!(machine.timers.delay(), 0);
+        assert_eq!(machine.timers.sound(), 0);
+    }
+
+    #[test]
+    fn test_execute_drw() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.mem.mem[0x300] = 0b1100_0000;
+        machine.mem.mem[0x301] = 0b0100_0000;
+        machine.i = 0x300;
+        machine.v[1] = 10;
+        machine.v[2] = 5;
+
+        machine
+            .execute(&Instruction::DisplaySprite(1, 2, 2))
+            .unwrap();
+        assert!(machine.display.pixel(10, 5));
+        assert!(machine.display.pixel(11, 5));
+        assert!(!machine.display.pixel(10, 6));
+        assert!(machine.display.pixel(11, 6));
+        assert_eq!(machine.v[FLAG_REGISTER], 0); // nothing was erased
+
+        // drawing it again erases the sprite and sets the collision flag
+        machine
+            .execute(&Instruction::DisplaySprite(1, 2, 2))
+            .unwrap();
+        assert!(machine.display.pixels().iter().all(|p| *p == 0));
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+
+        assert_eq!(machine.counter, 512);
+        assert_eq!(machine.i, 0x300);
+        assert_eq!(machine.stack_ptr, 0);
+        assert!(!machine.skip_increment);
+    }
+
+    #[test]
+    fn test_execute_cls_clears_display() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.mem.mem[0x300] = 0xFF;
+        machine.i = 0x300;
+        machine
+            .execute(&Instruction::DisplaySprite(0, 0, 1))
+            .unwrap();
+        assert!(machine.display.pixel(0, 0));
+
+        machine.execute(&Instruction::ClearScreen).unwrap();
+        assert!(machine.display.pixels().iter().all(|p| *p == 0));
+        assert_eq!(machine.counter, 512);
+    }
+
+    #[test]
+    fn test_execute_skp_sknp() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[3] = 0xA;
+
+        machine.execute(&Instruction::SkipKeyPress(3)).unwrap();
+        assert_eq!(machine.counter, 512);
+        machine.execute(&Instruction::SkipNotKeyPress(3)).unwrap();
+        assert_eq!(machine.counter, 514);
+
+        machine.press_key(0xA);
+        machine.execute(&Instruction::SkipKeyPress(3)).unwrap();
+        assert_eq!(machine.counter, 516);
+        machine.execute(&Instruction::SkipNotKeyPress(3)).unwrap();
+        assert_eq!(machine.counter, 516);
+
+        assert_eq!(machine.v[3], 0xA);
+        assert_eq!(machine.stack_ptr, 0);
+        assert!(!machine.skip_increment);
+    }
+
+    #[test]
+    fn test_execute_ld_key() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        // F50A: LD V5, K followed by 6101: LD V1, 0x01
+        machine.mem.mem[512..516].copy_from_slice(&[0xF5, 0x0A, 0x61, 0x01]);
+
+        machine.step().unwrap();
+        assert!(machine.is_waiting_for_key());
+        assert_eq!(machine.counter, 514);
+
+        // stepping while blocked does not execute anything
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 514);
+        assert_eq!(machine.v[1], 0);
+
+        // a press alone does not complete the instruction, the release does
+        machine.press_key(0x7);
+        assert!(machine.is_waiting_for_key());
+        machine.release_key(0x7);
+        assert!(!machine.is_waiting_for_key());
+        assert_eq!(machine.v[5], 0x7);
+
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 516);
+        assert_eq!(machine.v[1], 0x01);
+    }
+
+    #[test]
+    fn test_font_loaded() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        let offset = usize::from(FONT_OFFSET);
+        assert_eq!(machine.mem.mem[offset..offset + 80], STANDARD_GLYPHS[..]);
+
+        // reset puts the font back after memory is wiped
+        machine.mem.mem[offset] = 0;
+        machine.reset().unwrap();
+        assert_eq!(machine.mem.mem[offset..offset + 80], STANDARD_GLYPHS[..]);
+
+        machine.set_font(Font::new(0, VIP_GLYPHS)).unwrap();
+        assert_eq!(machine.mem.mem[..80], VIP_GLYPHS[..]);
+        assert_eq!(machine.mem.mem[80..240], LARGE_GLYPHS[..]);
+        assert!(machine.mem.mem[240..PROGRAM_OFFSET].iter().all(|b| *b == 0));
+
+        assert!(machine.set_font(Font::new(4090, VIP_GLYPHS)).is_err());
+        assert_eq!(machine.font().offset(), 0);
+    }
+
+    #[test]
+    fn test_execute_ld_font() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[4] = 0xB;
+        machine.execute(&Instruction::LoadFontSprite(4)).unwrap();
+        assert_eq!(machine.i, u32::from(FONT_OFFSET + 0xB * 5));
+        assert_eq!(machine.v[4], 0xB);
+        assert_eq!(machine.counter, 512);
+        assert!(!machine.skip_increment);
+
+        // drawing the glyph shows the digit
+        machine
+            .execute(&Instruction::DisplaySprite(0, 0, 5))
+            .unwrap();
+        assert!(machine.display.pixel(0, 0));
+        assert!(machine.display.pixel(2, 0));
+        assert!(!machine.display.pixel(3, 0));
+    }
+
+    #[test]
+    fn test_execute_timers() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[2] = 30;
+        machine.v[3] = 4;
+        machine.execute(&Instruction::LoadDelay(2)).unwrap();
+        machine.execute(&Instruction::LoadSound(3)).unwrap();
+        // the value of the register is loaded, not the register index
+        assert_eq!(machine.timers.delay(), 30);
+        assert_eq!(machine.timers.sound(), 4);
+        assert_eq!(machine.sound_edge(), Some(SoundEdge::Started));
+
+        machine.tick_timers();
+        machine.execute(&Instruction::LoadFromDelay(5)).unwrap();
+        assert_eq!(machine.v[5], 29);
+        assert_eq!(machine.counter, 512);
+    }
+
+    #[test]
+    fn test_run_frame() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        // 7101: ADD V1, 0x01 repeated over program memory
+        for pc in (PROGRAM_OFFSET..PROGRAM_OFFSET + 64).step_by(2) {
+            machine.mem.mem[pc] = 0x71;
+            machine.mem.mem[pc + 1] = 0x01;
+        }
+        machine.timers.set_delay(10);
+        machine.set_cycles_per_frame(5);
+
+        // the timers tick once per frame regardless of the instruction count
+        machine.run_frame().unwrap();
+        assert_eq!(machine.v[1], 5);
+        assert_eq!(machine.timers.delay(), 9);
+
+        machine.set_cycles_per_frame(20);
+        machine.run_frame().unwrap();
+        assert_eq!(machine.v[1], 25);
+        assert_eq!(machine.timers.delay(), 8);
+    }
+
+    #[test]
+    fn test_execute_sub() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[1] = 10;
+        machine.v[2] = 3;
+        machine.execute(&Instruction::SubRegister(1, 2)).unwrap();
+        assert_eq!(machine.v[1], 7);
+        assert_eq!(machine.v[2], 3);
+        assert_eq!(machine.v[FLAG_REGISTER], 1); // no borrow
+
+        machine.execute(&Instruction::SubRegister(2, 1)).unwrap();
+        assert_eq!(machine.v[2], 252); // 3 - 7 wraps around
+        assert_eq!(machine.v[FLAG_REGISTER], 0); // borrow
+
+        // equal values do not borrow
+        machine.v[3] = 5;
+        machine.v[4] = 5;
+        machine.execute(&Instruction::SubRegister(3, 4)).unwrap();
+        assert_eq!(machine.v[3], 0);
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+
+        assert_eq!(machine.counter, 512);
+        assert_eq!(machine.i, 0);
+        assert!(!machine.skip_increment);
+    }
+
+    #[test]
+    fn test_execute_subn() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[1] = 3;
+        machine.v[2] = 10;
+        machine.execute(&Instruction::SubNRegister(1, 2)).unwrap();
+        assert_eq!(machine.v[1], 7);
+        assert_eq!(machine.v[2], 10);
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+
+        machine.v[1] = 11;
+        machine.execute(&Instruction::SubNRegister(1, 2)).unwrap();
+        assert_eq!(machine.v[1], 255);
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+
+        assert_eq!(machine.counter, 512);
+        assert!(!machine.skip_increment);
+    }
+
+    #[test]
+    fn test_execute_flag_register_as_operand() {
+        // VF holds the flag afterwards even when it was the destination
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[FLAG_REGISTER] = 1;
+        machine.v[1] = 2;
+        machine.execute(&Instruction::SubRegister(15, 1)).unwrap();
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+
+        machine.v[FLAG_REGISTER] = 0b1000_0000;
+        machine.execute(&Instruction::ShiftLeft(15, 15)).unwrap();
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn test_execute_shr() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[1] = 0b0000_0101;
+        machine.execute(&Instruction::ShiftRight(1, 1)).unwrap();
+        assert_eq!(machine.v[1], 0b0000_0010);
+        assert_eq!(machine.v[FLAG_REGISTER], 1); // shifted out bit
+
+        machine.execute(&Instruction::ShiftRight(1, 1)).unwrap();
+        assert_eq!(machine.v[1], 0b0000_0001);
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+
+        assert_eq!(machine.counter, 512);
+        assert!(!machine.skip_increment);
+    }
+
+    #[test]
+    fn test_execute_shl() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[1] = 0b1100_0001;
+        machine.execute(&Instruction::ShiftLeft(1, 1)).unwrap();
+        assert_eq!(machine.v[1], 0b1000_0010);
+        assert_eq!(machine.v[FLAG_REGISTER], 1); // shifted out bit
+
+        machine.execute(&Instruction::ShiftLeft(1, 1)).unwrap();
+        assert_eq!(machine.v[1], 0b0000_0100);
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+
+        machine.execute(&Instruction::ShiftLeft(1, 1)).unwrap();
+        assert_eq!(machine.v[1], 0b0000_1000);
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+
+        assert_eq!(machine.counter, 512);
+        assert!(!machine.skip_increment);
+    }
+
+    #[test]
+    fn test_execute_sne_reg() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+
+        assert_eq!(machine.counter, 512); // before machine executes instruction
+        machine.v[1] = 0x0001;
+        machine.v[12] = 0x0001;
+        machine
+            .execute(&Instruction::SkipNotEqualRegister(1, 12))
+            .unwrap();
+        assert_eq!(machine.counter, 512);
+
+        machine.v[1] = 0x0002;
+        machine
+            .execute(&Instruction::SkipNotEqualRegister(1, 12))
+            .unwrap();
+        assert_eq!(machine.counter, 514);
+
+        assert_eq!(machine.stack_ptr, 0);
+        assert!(!machine.skip_increment);
+        assert_eq!(machine.i, 0);
+    }
+
+    #[test]
+    fn test_execute_jump_base() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+
+        assert_eq!(machine.counter, 512); // before machine executes instruction
+
+        machine.execute(&Instruction::JumpBase(0x0300)).unwrap();
+        assert_eq!(machine.counter, 0x0300);
+
+        machine.v[0] = 0x22;
+        machine.execute(&Instruction::JumpBase(0x0300)).unwrap();
+        assert_eq!(machine.counter, 0x0322);
+
+        assert_eq!(machine.stack_ptr, 0);
+        assert!(machine.skip_increment);
+        assert_eq!(machine.stack, [0; STACK_SIZE]);
+        assert_eq!(machine.i, 0);
+    }
+
+    #[test]
+    fn test_step_errors() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        // 00EE: RET with an empty stack
+        machine.mem.mem[512..514].copy_from_slice(&[0x00, 0xEE]);
+        let error = machine.step().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::StackUnderflow));
+        assert_eq!(error.pc(), Some(512));
+        assert_eq!(error.opcode(), Some(0x00EE));
+
+        // E0FF is not a valid opcode
+        machine.mem.mem[512..514].copy_from_slice(&[0xE0, 0xFF]);
+        let error = machine.step().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::InvalidOpcode));
+        assert_eq!(error.pc(), Some(512));
+        assert_eq!(error.opcode(), Some(0xE0FF));
+
+        machine.counter = 4095;
+        let error = machine.step().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::PcOutOfBounds));
+        assert_eq!(error.pc(), Some(4095));
+    }
+
+    #[test]
+    fn test_execute_call_overflow() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        // every slot of the stack is usable
+        for _ in 0..STACK_SIZE {
+            machine.execute(&Instruction::Call(0x0300)).unwrap();
+        }
+        let error = machine.execute(&Instruction::Call(0x0300)).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::StackOverflow));
+        assert_eq!(usize::from(machine.stack_ptr), STACK_SIZE);
+    }
+
+    #[test]
+    fn test_call_and_return() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        // 2300: CALL 0x300, 6101: LD V1, 0x01 ... 0x300: 00EE: RET
+        machine.mem.mem[512..516].copy_from_slice(&[0x23, 0x00, 0x61, 0x01]);
+        machine.mem.mem[0x300..0x302].copy_from_slice(&[0x00, 0xEE]);
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 0x300);
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 514);
+        assert_eq!(machine.stack_ptr, 0);
+        machine.step().unwrap();
+        assert_eq!(machine.v[1], 0x01);
+    }
+
+    #[test]
+    fn test_call_at_end_of_memory() {
+        let mut machine = Machine::builder("TestVM")
+            .platform(Platform::XoChip)
+            .build()
+            .unwrap();
+        assert_eq!(machine.mem.mem.len(), 0x10000);
+        // 2300: CALL 0x300 in the last word of memory
+        machine.mem.mem[0xFFFE..].copy_from_slice(&[0x23, 0x00]);
+        machine.counter = 0xFFFE;
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 0x300);
+        assert_eq!(machine.stack(), &[0x0000]);
+    }
+
+    #[test]
+    fn test_stack_policy_wrap() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.set_stack_policy(StackPolicy::Wrap);
+
+        machine.counter = 0x400;
+        machine.execute(&Instruction::Return).unwrap();
+        assert_eq!(usize::from(machine.stack_ptr), STACK_SIZE - 1);
+        assert_eq!(machine.counter, 0);
+
+        machine.stack_ptr = 0;
+        for n in 0..=STACK_SIZE as u16 {
+            machine.counter = n * 2;
+            machine.execute(&Instruction::Call(0x0300)).unwrap();
+        }
+        // the 17th call overwrote the oldest return address
+        assert_eq!(machine.stack_ptr, 1);
+        assert_eq!(machine.stack[0], STACK_SIZE as u16 * 2 + 2);
+        assert_eq!(machine.stack[1], 4);
+    }
+
+    #[test]
+    fn test_stack_policy_trap() {
+        use std::sync::{Arc, Mutex};
+
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.mem.mem[512..514].copy_from_slice(&[0x00, 0xEE]);
+        machine.set_stack_policy(StackPolicy::Trap);
+
+        // without a handler the machine still halts
+        assert!(machine.step().is_err());
+
+        let trapped = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&trapped);
+        machine.set_trap_handler(Box::new(move |error: &Error| {
+            log.lock().unwrap().push((error.pc(), error.opcode()));
+        }));
+        machine.step().unwrap();
+        assert_eq!(*trapped.lock().unwrap(), vec![(Some(512), Some(0x00EE))]);
+        // execution carries on with the next instruction
+        assert_eq!(machine.counter, 514);
+        assert_eq!(machine.stack_ptr, 0);
+    }
+
+    #[test]
+    fn test_execute_memory_out_of_bounds() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.i = 0xFFE;
+        let error = machine
+            .execute(&Instruction::StoreRegisters(3))
+            .unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::MemoryOutOfBounds(0x1000)));
+        // nothing was written
+        assert_eq!(machine.mem.mem[0xFFE..], [0, 0]);
+    }
+
+    #[test]
+    fn test_execute_bcd() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.v[6] = 254;
+        machine.i = 0x300;
+        machine.execute(&Instruction::LoadIBCD(6)).unwrap();
+        assert_eq!(machine.mem.mem[0x300..0x303], [2, 5, 4]);
+        assert_eq!(machine.i, 0x300);
+        assert_eq!(machine.counter, 512);
+    }
+
+    #[test]
+    fn test_execute_store_load_registers() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.v[..4].copy_from_slice(&[1, 2, 3, 4]);
+        machine.i = 0x300;
+        machine.execute(&Instruction::StoreRegisters(2)).unwrap();
+        assert_eq!(machine.mem.mem[0x300..0x304], [1, 2, 3, 0]);
+
+        machine.v = [0; REGISTER_COUNT];
+        machine.execute(&Instruction::LoadRegisters(1)).unwrap();
+        assert_eq!(machine.v[..4], [1, 2, 0, 0]);
+        assert_eq!(machine.i, 0x300);
+    }
+
+    #[test]
+    fn test_memory_policy_wrap() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.set_memory_policy(MemoryPolicy::Wrap);
+        machine.v[..3].copy_from_slice(&[7, 8, 9]);
+        machine.i = 0xFFE;
+        machine.execute(&Instruction::StoreRegisters(2)).unwrap();
+        assert_eq!(machine.mem.mem[0xFFE..], [7, 8]);
+        assert_eq!(machine.mem.mem[0], 9);
+
+        machine.v[1] = 0x03;
+        machine.v[FLAG_REGISTER] = 0;
+        machine.execute(&Instruction::AddI(1)).unwrap();
+        assert_eq!(machine.i, 0x001);
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+    }
+
+    #[test]
+    fn test_memory_policy_fault() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.set_memory_policy(MemoryPolicy::Fault);
+        machine.i = 0xFFE;
+        machine.v[1] = 0x02;
+        let error = machine.execute(&Instruction::AddI(1)).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::MemoryOutOfBounds(0x1000)));
+        assert_eq!(machine.i, 0xFFE);
+
+        let error = machine.execute(&Instruction::LoadIBCD(1)).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::MemoryOutOfBounds(0x1000)));
+        assert_eq!(machine.mem.mem[0xFFE..], [0, 0]);
+    }
+
+    #[test]
+    fn test_memory_policy_amiga() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.set_memory_policy(MemoryPolicy::AmigaOverflow);
+        machine.i = 0xFFE;
+        machine.v[1] = 0x01;
+        machine.execute(&Instruction::AddI(1)).unwrap();
+        assert_eq!(machine.i, 0xFFF);
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+
+        machine.execute(&Instruction::AddI(1)).unwrap();
+        assert_eq!(machine.i, 0x000);
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn test_protect_interpreter() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        machine.set_protect_interpreter(true);
+        machine.v[0] = 0xAA;
+        machine.i = 0x1FF;
+        let error = machine
+            .execute(&Instruction::StoreRegisters(1))
+            .unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::WriteProtected(0x1FF)));
+        assert_eq!(machine.mem.mem[0x200], 0);
+
+        // reading the font is still allowed
+        machine.execute(&Instruction::LoadFontSprite(1)).unwrap();
+        machine.execute(&Instruction::LoadRegisters(0)).unwrap();
+        assert_eq!(machine.v[0], 0xF0);
+    }
+
+    #[test]
+    fn test_quirk_shift() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::COSMAC_VIP).unwrap();
+        machine.v[1] = 0b0000_0001;
+        machine.v[2] = 0b1000_0010;
+        machine.execute(&Instruction::ShiftRight(1, 2)).unwrap();
+        assert_eq!(machine.v[1], 0b0100_0001);
+        assert_eq!(machine.v[2], 0b1000_0010);
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+        machine.execute(&Instruction::ShiftLeft(1, 2)).unwrap();
+        assert_eq!(machine.v[1], 0b0000_0100);
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.v[1] = 0b0000_0001;
+        machine.v[2] = 0b1000_0010;
+        machine.execute(&Instruction::ShiftRight(1, 2)).unwrap();
+        assert_eq!(machine.v[1], 0);
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn test_quirk_logic_resets_vf() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::COSMAC_VIP).unwrap();
+        machine.v[FLAG_REGISTER] = 1;
+        machine.execute(&Instruction::Or(1, 2)).unwrap();
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.v[FLAG_REGISTER] = 1;
+        machine.execute(&Instruction::Xor(1, 2)).unwrap();
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn test_quirk_jump_uses_vx() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.v[0] = 0x10;
+        machine.v[3] = 0x04;
+        machine.execute(&Instruction::JumpBase(0x0320)).unwrap();
+        assert_eq!(machine.counter, 0x0324);
+        assert!(machine.skip_increment);
+    }
+
+    #[test]
+    fn test_quirk_index_increment() {
+        let presets = [
+            (Quirks::COSMAC_VIP, 0x303),
+            (Quirks::CHIP_48, 0x302),
+            (Quirks::SUPER_CHIP, 0x300),
+        ];
+        for (quirks, expected) in presets.iter() {
+            let mut machine = Machine::new("TestVM", OpcodeMaskParser::default(), *quirks).unwrap();
+            machine.i = 0x300;
+            machine.execute(&Instruction::StoreRegisters(2)).unwrap();
+            assert_eq!(machine.i, *expected);
+            machine.i = 0x300;
+            machine.execute(&Instruction::LoadRegisters(2)).unwrap();
+            assert_eq!(machine.i, *expected);
+        }
+    }
+
+    #[test]
+    fn test_quirk_sprites_wrap() {
+        let mut quirks = Quirks::SUPER_CHIP;
+        quirks.sprites_wrap = true;
+        let mut machine = Machine::new("TestVM", OpcodeMaskParser::default(), quirks).unwrap();
+        machine.mem.mem[0x300] = 0xFF;
+        machine.i = 0x300;
+        machine.v[0] = 60;
+        machine
+            .execute(&Instruction::DisplaySprite(0, 1, 1))
+            .unwrap();
+        assert!(machine.display.pixel(3, 0));
+
+        machine.set_quirks(Quirks::SUPER_CHIP);
+        machine.display.clear();
+        machine
+            .execute(&Instruction::DisplaySprite(0, 1, 1))
+            .unwrap();
+        assert!(!machine.display.pixel(3, 0));
+    }
+
+    #[test]
+    fn test_quirk_display_wait() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::COSMAC_VIP).unwrap();
+        // D001: DRW V0, V0, 1 repeated over program memory
+        for pc in (PROGRAM_OFFSET..PROGRAM_OFFSET + 64).step_by(2) {
+            machine.mem.mem[pc] = 0xD0;
+            machine.mem.mem[pc + 1] = 0x01;
+        }
+        machine.run_frame().unwrap();
+        assert_eq!(machine.counter, 514); // only one sprite per frame
+        machine.run_frame().unwrap();
+        assert_eq!(machine.counter, 516);
+
+        machine.set_quirks(Quirks::SUPER_CHIP);
+        machine.run_frame().unwrap();
+        assert_eq!(
+            machine.counter,
+            516 + 2 * Platform::Chip8.cycles_per_frame() as u16
+        );
+    }
+
+    #[test]
+    fn test_execute_resolution_and_scroll() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.execute(&Instruction::HighResolution).unwrap();
+        assert_eq!(machine.display().width(), 128);
+        assert_eq!(machine.display().height(), 64);
+
+        machine.display.draw_sprite(0, 0, &[0x80]);
+        machine.execute(&Instruction::ScrollDown(3)).unwrap();
+        assert!(machine.display().pixel(0, 3));
+        machine.execute(&Instruction::ScrollRight).unwrap();
+        assert!(machine.display().pixel(4, 3));
+        machine.execute(&Instruction::ScrollLeft).unwrap();
+        assert!(machine.display().pixel(0, 3));
+
+        machine.execute(&Instruction::LowResolution).unwrap();
+        assert_eq!(machine.display().width(), 64);
+        assert!(machine.display().pixels().iter().all(|p| *p == 0));
+
+        machine.execute(&Instruction::HighResolution).unwrap();
+        machine.reset().unwrap();
+        assert!(!machine.display().is_high_resolution());
+    }
+
+    #[test]
+    fn test_execute_large_sprite() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.execute(&Instruction::HighResolution).unwrap();
+        machine.i = 0x300;
+        machine.mem.mem[0x300..0x320].copy_from_slice(&[0xFF; 32]);
+        machine.v[1] = 8;
+        machine.v[2] = 4;
+        machine
+            .execute(&Instruction::DisplayLargeSprite(1, 2))
+            .unwrap();
+        assert!(machine.display().pixel(8, 4));
+        assert!(machine.display().pixel(23, 19));
+        assert!(!machine.display().pixel(24, 19));
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+
+        machine
+            .execute(&Instruction::DisplayLargeSprite(1, 2))
+            .unwrap();
+        assert!(!machine.display().pixel(8, 4));
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+    }
+
+    #[test]
+    fn test_execute_ld_large_font() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        machine.v[4] = 0x3;
+        machine
+            .execute(&Instruction::LoadLargeFontSprite(4))
+            .unwrap();
+        assert_eq!(machine.i, u32::from(FONT_OFFSET + 80 + 0x3 * 10));
+        let i = machine.index();
+        assert_eq!(machine.mem.mem[i..i + 10], LARGE_GLYPHS[30..40]);
+    }
+
+    #[test]
+    fn test_execute_flags() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::SUPER_CHIP).unwrap();
+        for (index, register) in machine.v.iter_mut().enumerate() {
+            *register = index as u8 + 1;
+        }
+        machine.execute(&Instruction::StoreFlags(0xF)).unwrap();
+        assert_eq!(machine.rpl, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // the flags survive a reset
+        machine.reset().unwrap();
+        machine.execute(&Instruction::LoadFlags(2)).unwrap();
+        assert_eq!(machine.v[..4], [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_exit() {
+        let mut machine = Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::SuperChip),
+            Quirks::SUPER_CHIP,
+        )
+        .unwrap();
+        machine.load_rom_bytes(&[0x00, 0xFD, 0x70, 0x01]).unwrap();
+        machine.run_frame().unwrap();
+        assert!(machine.is_halted());
+        assert_eq!(machine.counter, 512);
+        assert_eq!(machine.v[0], 0);
+        machine.start().unwrap();
+
+        machine.reset().unwrap();
+        assert!(!machine.is_halted());
+    }
+
+    fn xo_chip_machine() -> Machine<OpcodeMaskParser> {
+        Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::XoChip),
+            Quirks::XO_CHIP,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_xo_chip_memory() {
+        let mut machine = xo_chip_machine();
+        assert_eq!(machine.mem.mem.len(), 0x10000);
+        machine.i = 0xFFFE;
+        machine.v[0] = 0xAB;
+        machine.execute(&Instruction::StoreRegisters(0)).unwrap();
+        assert_eq!(machine.mem.mem[0xFFFE], 0xAB);
+
+        let rom = vec![0xCC; 0x10000 - PROGRAM_OFFSET];
+        let summary = machine.load_rom_bytes(&rom).unwrap();
+        assert_eq!(summary.end_address, 0x10000);
+    }
+
+    #[test]
+    fn test_execute_long_load() {
+        let mut machine = xo_chip_machine();
+        machine
+            .load_rom_bytes(&[0xF0, 0x00, 0xBE, 0xEF, 0x60, 0x01])
+            .unwrap();
+        machine.step().unwrap();
+        assert_eq!(machine.i, 0xBEEF);
+        assert_eq!(machine.counter, 516);
+        machine.step().unwrap();
+        assert_eq!(machine.v[0], 1);
+    }
+
+    #[test]
+    fn test_skip_long_load() {
+        let mut machine = xo_chip_machine();
+        // SE V0, 0 skips over the whole of F000 nnnn
+        machine
+            .load_rom_bytes(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x60, 0x01])
+            .unwrap();
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 518);
+
+        // other instruction sets know nothing of four byte instructions
+        let mut machine = Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::SuperChip),
+            Quirks::SUPER_CHIP,
+        )
+        .unwrap();
+        machine
+            .load_rom_bytes(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34])
+            .unwrap();
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 516);
+    }
+
+    #[test]
+    fn test_execute_save_load_range() {
+        let mut machine = xo_chip_machine();
+        machine.i = 0x300;
+        machine.v[2] = 1;
+        machine.v[3] = 2;
+        machine.v[4] = 3;
+        machine.execute(&Instruction::SaveRange(2, 4)).unwrap();
+        assert_eq!(machine.mem.mem[0x300..0x303], [1, 2, 3]);
+        assert_eq!(machine.i, 0x300);
+
+        // a descending range is stored starting with Vx
+        machine.execute(&Instruction::SaveRange(4, 2)).unwrap();
+        assert_eq!(machine.mem.mem[0x300..0x303], [3, 2, 1]);
+
+        machine.execute(&Instruction::LoadRange(7, 9)).unwrap();
+        assert_eq!(machine.v[7..10], [3, 2, 1]);
+        machine.execute(&Instruction::LoadRange(9, 9)).unwrap();
+        assert_eq!(machine.v[9], 3);
+        assert_eq!(machine.i, 0x300);
+    }
+
+    #[test]
+    fn test_execute_planes() {
+        let mut machine = xo_chip_machine();
+        machine.i = 0x300;
+        machine.mem.mem[0x300] = 0x80;
+        machine.mem.mem[0x301] = 0xC0;
+        machine.execute(&Instruction::SelectPlanes(3)).unwrap();
+        machine
+            .execute(&Instruction::DisplaySprite(0, 0, 1))
+            .unwrap();
+        assert_eq!(machine.display().color(0, 0), 3);
+        assert_eq!(machine.display().color(1, 0), 2);
+
+        machine.execute(&Instruction::SelectPlanes(2)).unwrap();
+        machine.execute(&Instruction::ClearScreen).unwrap();
+        assert_eq!(machine.display().color(0, 0), 1);
+        assert_eq!(machine.display().color(1, 0), 0);
+    }
+
+    #[test]
+    fn test_execute_audio() {
+        let mut machine = xo_chip_machine();
+        machine.i = 0x300;
+        for (n, byte) in machine.mem.mem[0x300..0x310].iter_mut().enumerate() {
+            *byte = n as u8;
+        }
+        machine.execute(&Instruction::LoadAudio).unwrap();
+        assert_eq!(machine.audio().pattern()[15], 15);
+        machine.v[1] = 112;
+        machine.execute(&Instruction::LoadPitch(1)).unwrap();
+        assert_eq!(machine.audio().pitch(), 112);
+
+        machine.reset().unwrap();
+        assert_eq!(machine.audio(), &AudioPattern::new());
+    }
+
+    fn chip8x_machine() -> Machine<OpcodeMaskParser> {
+        Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::Chip8X),
+            Quirks::COSMAC_VIP,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_chip8x_program_start() {
+        let mut machine = chip8x_machine();
+        assert_eq!(machine.load_address(), 0x300);
+        assert_eq!(machine.counter, 0x300);
+        let summary = machine.load_rom_bytes(&[0x02, 0xA0]).unwrap();
+        assert_eq!(summary.start_address, 0x300);
+        machine.step().unwrap();
+        assert_eq!(machine.colors().background(), 1);
+        assert_eq!(machine.counter, 0x302);
+    }
+
+    #[test]
+    fn test_execute_add_nibbles() {
+        let mut machine = chip8x_machine();
+        machine.v[1] = 0x36;
+        machine.v[2] = 0x57;
+        machine.execute(&Instruction::AddNibbles(1, 2)).unwrap();
+        assert_eq!(machine.v[1], 0x05);
+        assert_eq!(machine.v[2], 0x57);
+    }
+
+    #[test]
+    fn test_execute_colors() {
+        let mut machine = chip8x_machine();
+        machine.v[4] = 0x00; // zone column 0
+        machine.v[5] = 0x10; // zone rows 0 and 1
+        machine.v[6] = 2;
+        machine.execute(&Instruction::SetColorZones(4, 6)).unwrap();
+        assert_eq!(machine.colors().foreground(7, 7), 2);
+        assert_eq!(machine.colors().foreground(8, 7), 1);
+
+        machine.v[4] = 20;
+        machine.v[5] = 30;
+        machine
+            .execute(&Instruction::SetColorRows(4, 6, 2))
+            .unwrap();
+        assert_eq!(machine.colors().foreground(16, 31), 2);
+        assert_eq!(machine.colors().foreground(16, 29), 1);
+
+        machine.reset().unwrap();
+        assert_eq!(machine.colors(), &ColorAttributes::new());
+    }
+
+    #[test]
+    fn test_execute_second_keypad() {
+        let mut machine = chip8x_machine();
+        machine.v[0] = 0x5;
+        machine.press_key(0x5);
+        machine
+            .execute(&Instruction::SkipSecondKeyPress(0))
+            .unwrap();
+        assert_eq!(machine.counter, 0x300);
+        machine
+            .execute(&Instruction::SkipNotSecondKeyPress(0))
+            .unwrap();
+        assert_eq!(machine.counter, 0x302);
+
+        machine.press_second_key(0x5);
+        machine
+            .execute(&Instruction::SkipSecondKeyPress(0))
+            .unwrap();
+        assert_eq!(machine.counter, 0x304);
+        machine.release_second_key(0x5);
+        assert!(!machine.second_keypad().is_pressed(0x5));
+    }
+
+    fn chip8e_machine() -> Machine<OpcodeMaskParser> {
+        Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::Chip8E),
+            Quirks::COSMAC_VIP,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_execute_chip8e_ranges() {
+        let mut machine = chip8e_machine();
+        machine.i = 0x300;
+        machine.v[..3].copy_from_slice(&[7, 8, 9]);
+        machine.execute(&Instruction::SaveRange(0, 2)).unwrap();
+        assert_eq!(machine.mem.mem[0x300..0x303], [7, 8, 9]);
+        assert_eq!(machine.i, 0x303);
+
+        machine.i = 0x300;
+        machine.execute(&Instruction::LoadRange(5, 4)).unwrap();
+        assert_eq!(machine.v[4..6], [8, 7]);
+        assert_eq!(machine.i, 0x302);
+    }
+
+    #[test]
+    fn test_execute_chip8e_jumps_and_skips() {
+        let mut machine = chip8e_machine();
+        machine.counter = 0x210;
+        machine.execute(&Instruction::JumpBack(6)).unwrap();
+        assert_eq!(machine.counter, 0x20C);
+        machine.execute(&Instruction::JumpForward(0x10)).unwrap();
+        assert_eq!(machine.counter, 0x21E);
+
+        machine.v[1] = 3;
+        machine.execute(&Instruction::SkipBytes(1)).unwrap();
+        assert_eq!(machine.counter, 0x221);
+        machine.execute(&Instruction::Skip).unwrap();
+        assert_eq!(machine.counter, 0x223);
+
+        machine.v[2] = 4;
+        machine.execute(&Instruction::SkipGreater(1, 2)).unwrap();
+        assert_eq!(machine.counter, 0x223);
+        machine.execute(&Instruction::SkipGreater(2, 1)).unwrap();
+        assert_eq!(machine.counter, 0x225);
+
+        machine.execute(&Instruction::OutputPort(2)).unwrap();
+        assert_eq!(machine.output_port(), 4);
+    }
+
+    #[test]
+    fn test_chip8e_wait_for_delay() {
+        let mut machine = chip8e_machine();
+        // LD V0, 2; LD DT, V0 and wait; LD V1, 1
+        machine
+            .load_rom_bytes(&[0x60, 0x02, 0xF0, 0x4F, 0x61, 0x01])
+            .unwrap();
+        machine.run_frame().unwrap();
+        assert_eq!(machine.counter, 516);
+        assert_eq!(machine.timers().delay(), 1);
+        machine.run_frame().unwrap();
+        assert_eq!(machine.v[1], 0);
+        machine.run_frame().unwrap();
+        assert_eq!(machine.v[1], 1);
+
+        // STOP
+        machine.load_rom_bytes(&[0x00, 0xED]).unwrap();
+        machine.counter = 512;
+        machine.step().unwrap();
+        assert!(machine.is_halted());
+    }
+
+    #[test]
+    fn test_vip_hires_detection() {
+        let mut machine =
+            Machine::new("TestVM", OpcodeMaskParser::default(), Quirks::default()).unwrap();
+        let mut rom = vec![0; 0xD0];
+        rom[..2].copy_from_slice(&[0x12, 0x60]);
+        rom[0xC0..0xC2].copy_from_slice(&[0x02, 0x30]); // CLS at 0x2C0
+        machine.load_rom_bytes(&rom).unwrap();
+        assert!(machine.is_vip_hires());
+        assert_eq!(machine.counter, 0x2C0);
+        assert_eq!(machine.display().height(), 64);
+
+        machine.display.draw_sprite(0, 63, &[0x80]);
+        machine.step().unwrap();
+        assert!(machine.display().pixels().iter().all(|p| *p == 0));
+
+        machine.reset().unwrap();
+        assert_eq!(machine.counter, 0x2C0);
+        assert_eq!(machine.display().height(), 64);
+
+        machine.set_vip_hires(false);
+        assert_eq!(machine.counter, 512);
+        assert_eq!(machine.display().height(), 32);
+
+        // other instruction sets and load addresses are left alone
+        let mut machine = Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::SuperChip),
+            Quirks::SUPER_CHIP,
+        )
+        .unwrap();
+        machine.load_rom_bytes(&[0x12, 0x60]).unwrap();
+        assert!(!machine.is_vip_hires());
+        assert_eq!(machine.counter, 512);
+    }
+
+    fn mega_chip_machine() -> Machine<OpcodeMaskParser> {
+        Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::MegaChip),
+            Quirks::SUPER_CHIP,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mega_chip_long_load() {
+        let mut machine = mega_chip_machine();
+        assert_eq!(machine.mem.mem.len(), 32 * 1024 * 1024);
+        // LDHI I, 0x123456; SE V0, 0; LDHI I, 0; LD V1, 1
+        machine
+            .load_rom_bytes(&[
+                0x01, 0x12, 0x34, 0x56, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x61, 0x01,
+            ])
+            .unwrap();
+        machine.step().unwrap();
+        assert_eq!(machine.i, 0x12_3456);
+        assert_eq!(machine.counter, 516);
+        machine.step().unwrap();
+        assert_eq!(machine.counter, 522);
+        machine.step().unwrap();
+        assert_eq!(machine.v[1], 1);
+    }
+
+    #[test]
+    fn test_mega_chip_sprites() {
+        let mut machine = mega_chip_machine();
+        machine.execute(&Instruction::MegaOn).unwrap();
+        assert!(machine.is_mega_mode());
+        machine.i = 0x10_0000;
+        machine.mem.mem[0x10_0000..0x10_0008]
+            .copy_from_slice(&[0xFF, 0x11, 0x22, 0x33, 0xFF, 0x44, 0x55, 0x66]);
+        machine.execute(&Instruction::LoadPalette(2)).unwrap();
+        assert_eq!(machine.mega_display().palette()[2], 0xFF44_5566);
+
+        machine.execute(&Instruction::SpriteWidth(2)).unwrap();
+        machine.execute(&Instruction::SpriteHeight(1)).unwrap();
+        machine.execute(&Instruction::CollisionColor(1)).unwrap();
+        machine.i = 0x300;
+        machine.mem.mem[0x300..0x302].copy_from_slice(&[1, 2]);
+        machine.v[0] = 200;
+        machine.v[1] = 100;
+        machine
+            .execute(&Instruction::DisplaySprite(0, 1, 0))
+            .unwrap();
+        assert_eq!(machine.mega_display().pixel(200, 100), 0xFF11_2233);
+        assert_eq!(machine.mega_display().pixel(201, 100), 0xFF44_5566);
+        assert_eq!(machine.v[FLAG_REGISTER], 0);
+        // the monochrome display is left alone
+        assert!(machine.display().pixels().iter().all(|p| *p == 0));
+
+        machine
+            .execute(&Instruction::DisplaySprite(0, 1, 0))
+            .unwrap();
+        assert_eq!(machine.v[FLAG_REGISTER], 1);
+
+        machine.execute(&Instruction::ClearScreen).unwrap();
+        assert_eq!(machine.mega_display().pixel(200, 100), 0xFF00_0000);
+        machine.execute(&Instruction::MegaOff).unwrap();
+        assert!(!machine.is_mega_mode());
+    }
+
+    #[test]
+    fn test_mega_chip_sample() {
+        let mut machine = mega_chip_machine();
+        machine.i = 0x400;
+        machine.mem.mem[0x400..0x409]
+            .copy_from_slice(&[0x1F, 0x40, 0x00, 0x00, 0x03, 0x00, 0x80, 0x90, 0xA0]);
+        machine.execute(&Instruction::PlaySample(1)).unwrap();
+        let sample = machine.sample().unwrap();
+        assert_eq!(sample.rate, 8000);
+        assert_eq!(sample.data, vec![0x80, 0x90, 0xA0]);
+        assert!(!sample.looping);
+
+        machine.execute(&Instruction::StopSample).unwrap();
+        assert!(machine.sample().is_none());
+    }
+
+    #[test]
+    fn test_eti660() {
+        let mut machine = Machine::builder("TestVM")
+            .platform(Platform::Eti660)
+            .build()
+            .unwrap();
+        assert_eq!(machine.load_address(), 0x600);
+        assert_eq!(machine.counter, 0x600);
+        assert_eq!(machine.display().height(), 48);
+
+        // JP 0x604 lands inside the program
+        let summary = machine
+            .load_rom_bytes(&[0x16, 0x04, 0x00, 0x00, 0x60, 0x2F])
+            .unwrap();
+        assert_eq!(summary.start_address, 0x600);
+        machine.step().unwrap();
+        machine.step().unwrap();
+        assert_eq!(machine.v[0], 0x2F);
+
+        machine.v[1] = 40;
+        machine.i = u32::from(FONT_OFFSET);
+        machine
+            .execute(&Instruction::DisplaySprite(1, 1, 1))
+            .unwrap();
+        assert!(machine.display().pixel(40, 40));
+
+        machine.reset().unwrap();
+        assert_eq!(machine.counter, 0x600);
+        assert_eq!(machine.display().height(), 48);
+    }
+
+    #[test]
+    fn test_eti660_low_resolution() {
+        let mut machine = Machine::builder("TestVM")
+            .platform(Platform::Eti660)
+            .build()
+            .unwrap();
+        machine.execute(&Instruction::HighResolution).unwrap();
+        assert_eq!(machine.display().height(), 64);
+        machine.execute(&Instruction::LowResolution).unwrap();
+        assert_eq!(
+            (machine.display().width(), machine.display().height()),
+            (64, 48)
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let machine = Machine::builder("TestVM").build().unwrap();
+        assert_eq!(machine.mem.mem.len(), 4096);
+        assert_eq!(machine.stack.len(), 16);
+        assert_eq!(machine.counter, 0x200);
+        assert_eq!(*machine.quirks(), Quirks::COSMAC_VIP);
+        assert_eq!(machine.cycles_per_frame(), 10);
+
+        let machine = Machine::builder("TestVM")
+            .platform(Platform::XoChip)
+            .cycles_per_frame(100)
+            .build()
+            .unwrap();
+        assert_eq!(
+            machine.instruction_parser.instruction_set(),
+            InstructionSet::XoChip
+        );
+        assert_eq!(machine.mem.mem.len(), 0x10000);
+        assert_eq!(*machine.quirks(), Quirks::XO_CHIP);
+        assert_eq!(machine.cycles_per_frame(), 100);
+
+        let machine = Machine::builder("TestVM")
+            .platform(Platform::Chip8X)
+            .quirks(Quirks::CHIP_48)
+            .build()
+            .unwrap();
+        assert_eq!(machine.counter, 0x300);
+        assert_eq!(*machine.quirks(), Quirks::CHIP_48);
+
+        let machine = Machine::builder("TestVM")
+            .platform(Platform::VipHires)
+            .build()
+            .unwrap();
+        assert!(machine.is_vip_hires());
+        assert_eq!(machine.display().height(), 64);
+        assert_eq!(machine.counter, 0x2C0);
+    }
+
+    #[test]
+    fn test_platform_stack_depth() {
+        let mut machine = Machine::builder("TestVM")
+            .platform(Platform::Chip8E)
+            .build()
+            .unwrap();
+        for _ in 0..12 {
+            machine.push(0x200).unwrap();
+        }
+        let error = machine.push(0x200).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::StackOverflow));
+        // the stack keeps its depth across a reset
+        machine.reset().unwrap();
+        assert_eq!(machine.stack, [0; 12]);
+
+        // `new` sizes the machine for the platform just like the builder
+        let machine = Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(InstructionSet::Chip8E),
+            Quirks::default(),
+        )
+        .unwrap();
+        assert_eq!(machine.config(), Platform::Chip8E.config());
+    }
+
+    #[test]
+    fn test_new_matches_builder() {
+        let platform = Platform::SuperChip;
+        let built = Machine::builder("TestVM")
+            .platform(platform)
+            .build()
+            .unwrap();
+        let new = Machine::new(
+            "TestVM",
+            OpcodeMaskParser::new(platform.instruction_set()),
+            platform.quirks(),
+        )
+        .unwrap();
+        assert_eq!(new.cycles_per_frame(), platform.cycles_per_frame());
+        assert_eq!(new.cycles_per_frame(), built.cycles_per_frame());
+        assert_eq!(new.config(), built.config());
+        assert_eq!(new.pc(), built.pc());
+        assert_eq!(new.display().height(), built.display().height());
+    }
+
+    #[test]
+    fn test_config_2k_vip() {
+        let mut machine = Machine::builder("TestVM")
+            .memory_size(0x800)
+            .stack_size(12)
+            .build()
+            .unwrap();
+        assert_eq!(
+            machine.config(),
+            MachineConfig {
+                memory_size: 0x800,
+                stack_size: 12,
+                program_offset: 0x200,
+            }
+        );
+        let error = machine.load_rom_bytes(&[0; 0x601]).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::RomTooLarge {
+                size: 0x601,
+                capacity: 0x600
+            }
+        ));
+        machine.load_rom_bytes(&[0; 0x600]).unwrap();
+
+        machine.i = 0x7FF;
+        let error = machine
+            .execute(&Instruction::StoreRegisters(1))
+            .unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::MemoryOutOfBounds(0x800)));
+
+        machine.reset().unwrap();
+        assert_eq!(machine.config().memory_size, 0x800);
+    }
+
+    #[test]
+    fn test_config_deep_stack_and_offset() {
+        let mut machine = Machine::builder("TestVM")
+            .stack_size(64)
+            .program_offset(0x400)
+            .build()
+            .unwrap();
+        for _ in 0..64 {
+            machine.push(0x400).unwrap();
+        }
+        assert!(machine.push(0x400).is_err());
+
+        assert_eq!(machine.counter, 0x400);
+        machine.set_protect_interpreter(true);
+        machine.i = 0x3FF;
+        let error = machine
+            .execute(&Instruction::StoreRegisters(0))
+            .unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::WriteProtected(0x3FF)));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let invalid = |builder: MachineBuilder| {
+            let error = builder.build().unwrap_err();
+            assert!(matches!(error.kind(), ErrorKind::InvalidConfig(_)));
+        };
+        invalid(Machine::builder("TestVM").stack_size(0));
+        invalid(Machine::builder("TestVM").stack_size(256));
+        invalid(Machine::builder("TestVM").memory_size(0x100));
+        invalid(Machine::builder("TestVM").memory_size(0x400_0000));
+        invalid(Machine::builder("TestVM").program_offset(0xFFF));
+        assert!(MachineConfig::default().validate(&Font::default()).is_ok());
+
+        // fonts have to fit in 16-bit addresses however much memory there is
+        let config = Platform::MegaChip.config();
+        assert!(config.validate(&Font::new(0xFF00, STANDARD_GLYPHS)).is_ok());
+        for font in [
+            Font::new(0xFFC0, STANDARD_GLYPHS),
+            Font::default().with_large_glyphs(0xFFF0, LARGE_GLYPHS),
+        ]
+        .iter()
+        {
+            let error = config.validate(font).unwrap_err();
+            assert!(matches!(error.kind(), ErrorKind::InvalidConfig(_)));
+        }
     }
 }
@@ -0,0 +1,367 @@
+use std::fmt;
+
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+// The two page display of the VIP hi-res interpreter.
+pub const VIP_HIRES_HEIGHT: usize = 64;
+pub const ETI_660_HEIGHT: usize = 48;
+pub const PLANE_COUNT: usize = 2;
+
+/**
+ * A framebuffer of up to two bitplanes.
+ * Pixels are stored row by row, each one holding a bit per plane, so its
+ * value is one of four colours with 0 meaning the pixel is off. Plain CHIP-8
+ * and SUPER-CHIP programs only ever draw on the first plane; XO-CHIP programs
+ * select which planes drawing, scrolling and clearing apply to.
+ * Sprites are XOR-ed onto the screen: drawing over a lit pixel turns it off
+ * and is reported back as a collision.
+ * SUPER-CHIP programs can switch it between the normal 64x32 resolution and
+ * a 128x64 high resolution mode, the VIP hi-res interpreter uses 64x64 and
+ * the ETI-660 64x48.
+*/
+pub struct Display {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    planes: u8, // bitmask of the selected planes
+    wrap: bool,
+}
+
+impl fmt::Debug for Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.pixels.chunks(self.width) {
+            for pixel in row {
+                write!(f, "{}", ['.', '#', '+', '@'][usize::from(*pixel & 3)])?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display {
+    pub fn new() -> Self {
+        Self {
+            width: DISPLAY_WIDTH,
+            height: DISPLAY_HEIGHT,
+            pixels: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            planes: 1,
+            wrap: false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // The colour of every pixel, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    // Whether the pixel is lit on any plane.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.color(x, y) != 0
+    }
+
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    // Only the two lowest bits select planes, 0 selects none.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & 0b11;
+    }
+
+    pub fn plane_count(&self) -> usize {
+        self.planes.count_ones() as usize
+    }
+
+    pub fn is_high_resolution(&self) -> bool {
+        self.width == HIRES_WIDTH
+    }
+
+    pub fn set_high_resolution(&mut self, high: bool) {
+        if high {
+            self.set_resolution(HIRES_WIDTH, HIRES_HEIGHT);
+        } else {
+            self.set_resolution(DISPLAY_WIDTH, DISPLAY_HEIGHT);
+        }
+    }
+
+    // Switching resolution clears the screen.
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; width * height];
+    }
+
+    // Sprites that cross the edge of the screen are clipped unless wrapping is enabled.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    // Clears the selected planes.
+    pub fn clear(&mut self) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !self.planes;
+        }
+    }
+
+    // The selected planes, lowest first.
+    fn selected_planes(&self) -> impl Iterator<Item = u8> {
+        let planes = self.planes;
+        (0..PLANE_COUNT)
+            .map(|plane| 1 << plane)
+            .filter(move |bit| planes & bit != 0)
+    }
+
+    /**
+     * XOR an 8 pixel wide sprite onto the screen with its top-left corner at (x, y).
+     * With several planes selected the sprite holds the rows for each of
+     * them in turn, lowest plane first.
+     * The starting position always wraps around the screen, the rest of the
+     * sprite is either clipped or wrapped depending on the display setting.
+     * Returns true if any lit pixel was turned off.
+     */
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let len = sprite.len() / self.plane_count().max(1);
+        let mut collision = false;
+        for (plane, data) in self.selected_planes().zip(sprite.chunks(len.max(1))) {
+            let rows = data.iter().map(|byte| u16::from(*byte) << 8);
+            collision |= self.draw_rows(x, y, 8, plane, rows);
+        }
+        collision
+    }
+
+    // Same as draw_sprite for the 16x16 SUPER-CHIP sprites, two bytes per row.
+    pub fn draw_large_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let len = sprite.len() / self.plane_count().max(1);
+        let mut collision = false;
+        for (plane, data) in self.selected_planes().zip(sprite.chunks(len.max(1))) {
+            let rows = data
+                .chunks(2)
+                .map(|row| u16::from(row[0]) << 8 | u16::from(*row.get(1).unwrap_or(&0)));
+            collision |= self.draw_rows(x, y, 16, plane, rows);
+        }
+        collision
+    }
+
+    // Each row holds the sprite's pixels left to right starting at the highest bit.
+    fn draw_rows<I>(&mut self, x: usize, y: usize, width: usize, plane: u8, rows: I) -> bool
+    where
+        I: Iterator<Item = u16>,
+    {
+        let x = x % self.width;
+        let y = y % self.height;
+        let mut collision = false;
+        for (row, bits) in rows.enumerate() {
+            let mut py = y + row;
+            if py >= self.height {
+                if !self.wrap {
+                    break;
+                }
+                py %= self.height;
+            }
+            for bit in 0..width {
+                if bits & (0x8000 >> bit) == 0 {
+                    continue;
+                }
+                let mut px = x + bit;
+                if px >= self.width {
+                    if !self.wrap {
+                        break;
+                    }
+                    px %= self.width;
+                }
+                let pixel = &mut self.pixels[py * self.width + px];
+                collision |= *pixel & plane != 0;
+                *pixel ^= plane;
+            }
+        }
+        collision
+    }
+
+    // Scroll the screen contents down by `rows`, blanking the rows scrolled in.
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.scroll(0, rows as isize);
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.scroll(0, -(rows as isize));
+    }
+
+    pub fn scroll_right(&mut self, columns: usize) {
+        self.scroll(columns as isize, 0);
+    }
+
+    pub fn scroll_left(&mut self, columns: usize) {
+        self.scroll(-(columns as isize), 0);
+    }
+
+    // Move the selected planes by (dx, dy), pixels scrolled off the screen are lost.
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let source = self.pixels.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&sx) && (0..height).contains(&sy) {
+                    source[(sy * width + sx) as usize] & self.planes
+                } else {
+                    0
+                };
+                let pixel = &mut self.pixels[(y * width + x) as usize];
+                *pixel = *pixel & !self.planes | moved;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_sprite_xor() {
+        let mut display = Display::new();
+        assert!(!display.draw_sprite(0, 0, &[0b1010_0000]));
+        assert!(display.pixel(0, 0));
+        assert!(!display.pixel(1, 0));
+        assert!(display.pixel(2, 0));
+
+        // drawing the same sprite again erases it and reports a collision
+        assert!(display.draw_sprite(0, 0, &[0b1010_0000]));
+        assert!(display.pixels().iter().all(|p| *p == 0));
+    }
+
+    #[test]
+    fn test_draw_sprite_clip() {
+        let mut display = Display::new();
+        display.draw_sprite(62, 31, &[0xFF, 0xFF]);
+        assert_eq!(display.pixels().iter().filter(|p| **p != 0).count(), 2);
+        assert!(display.pixel(62, 31));
+        assert!(display.pixel(63, 31));
+        assert!(!display.pixel(0, 31));
+        assert!(!display.pixel(62, 0));
+    }
+
+    #[test]
+    fn test_draw_sprite_wrap() {
+        let mut display = Display::new();
+        display.set_wrap(true);
+        display.draw_sprite(62, 31, &[0b1110_0000, 0b1000_0000]);
+        assert!(display.pixel(62, 31));
+        assert!(display.pixel(63, 31));
+        assert!(display.pixel(0, 31));
+        assert!(display.pixel(62, 0));
+
+        // the starting position wraps even when clipping
+        let mut display = Display::new();
+        display.draw_sprite(64 + 3, 32 + 1, &[0x80]);
+        assert!(display.pixel(3, 1));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut display = Display::new();
+        display.draw_sprite(10, 10, &[0xFF; 5]);
+        display.clear();
+        assert!(display.pixels().iter().all(|p| *p == 0));
+    }
+
+    #[test]
+    fn test_high_resolution() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &[0xFF]);
+        display.set_high_resolution(true);
+        assert_eq!((display.width(), display.height()), (128, 64));
+        assert!(display.pixels().iter().all(|p| *p == 0));
+        display.draw_sprite(120, 60, &[0xFF]);
+        assert!(display.pixel(127, 60));
+        display.set_high_resolution(false);
+        assert_eq!(display.pixels().len(), 64 * 32);
+
+        display.set_resolution(DISPLAY_WIDTH, VIP_HIRES_HEIGHT);
+        assert!(!display.is_high_resolution());
+        display.draw_sprite(0, 40, &[0x80]);
+        assert!(display.pixel(0, 40));
+    }
+
+    #[test]
+    fn test_draw_large_sprite() {
+        let mut display = Display::new();
+        let mut sprite = [0; 32];
+        sprite[0] = 0x80;
+        sprite[1] = 0x01;
+        sprite[31] = 0x01;
+        assert!(!display.draw_large_sprite(0, 0, &sprite));
+        assert!(display.pixel(0, 0));
+        assert!(display.pixel(15, 0));
+        assert!(display.pixel(15, 15));
+        assert_eq!(display.pixels().iter().filter(|p| **p != 0).count(), 3);
+        assert!(display.draw_large_sprite(0, 0, &sprite));
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &[0x80]);
+        display.scroll_down(3);
+        assert!(display.pixel(0, 3));
+        assert!(!display.pixel(0, 0));
+
+        display.scroll_right(4);
+        assert!(display.pixel(4, 3));
+        display.scroll_left(4);
+        assert!(display.pixel(0, 3));
+
+        // pixels scrolled off the screen are lost, not wrapped
+        display.scroll_left(4);
+        assert!(display.pixels().iter().all(|p| *p == 0));
+    }
+
+    #[test]
+    fn test_planes() {
+        let mut display = Display::new();
+        display.select_planes(2);
+        assert!(!display.draw_sprite(0, 0, &[0x80]));
+        assert_eq!(display.color(0, 0), 2);
+
+        // with both planes selected the sprite holds one set of rows per plane
+        display.select_planes(3);
+        assert!(display.draw_sprite(0, 0, &[0xC0, 0xC0]));
+        assert_eq!(display.color(0, 0), 1);
+        assert_eq!(display.color(1, 0), 3);
+
+        display.select_planes(1);
+        display.scroll_right(1);
+        assert_eq!(display.color(0, 0), 0);
+        assert_eq!(display.color(1, 0), 3);
+        assert_eq!(display.color(2, 0), 1);
+        display.clear();
+        assert_eq!(display.color(1, 0), 2);
+        assert_eq!(display.pixels().iter().filter(|p| **p != 0).count(), 1);
+
+        display.select_planes(0);
+        assert!(!display.draw_sprite(0, 0, &[0xFF]));
+        assert!(!display.pixel(0, 0));
+    }
+}
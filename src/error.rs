@@ -0,0 +1,147 @@
+use std::fmt;
+use std::io;
+
+use crate::instructions::Instruction;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    InvalidOpcode,
+    UnimplementedInstruction(Instruction),
+    StackOverflow,
+    StackUnderflow,
+    MemoryOutOfBounds(usize), // the first address that could not be accessed
+    WriteProtected(usize),
+    PcOutOfBounds,
+    RomTooLarge { size: usize, capacity: usize },
+    UnknownPlatform(String),
+    InvalidConfig(String),
+    UnknownImageFormat(String), // the path whose extension wasn't recognised
+    UnknownVideoFormat(String),
+    GifTooLarge { width: usize, height: usize },
+    MachineStopped, // the thread running the machine has ended
+    Io(io::Error),
+}
+
+/**
+ * An error raised while loading, decoding or executing a program.
+ * Besides the kind of failure it records, when known, the program counter
+ * and opcode of the instruction that caused it.
+*/
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    pc: Option<u16>,
+    opcode: Option<u16>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            pc: None,
+            opcode: None,
+        }
+    }
+
+    pub fn invalid_opcode(opcode: u16) -> Self {
+        Self::new(ErrorKind::InvalidOpcode).with_opcode(opcode)
+    }
+
+    pub fn with_pc(mut self, pc: u16) -> Self {
+        self.pc = Some(pc);
+        self
+    }
+
+    pub fn with_opcode(mut self, opcode: u16) -> Self {
+        self.opcode = Some(opcode);
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn pc(&self) -> Option<u16> {
+        self.pc
+    }
+
+    pub fn opcode(&self) -> Option<u16> {
+        self.opcode
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::new(ErrorKind::Io(error))
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidOpcode => write!(f, "invalid opcode"),
+            ErrorKind::UnimplementedInstruction(ins) => {
+                write!(f, "unimplemented instruction {:X?}", ins)
+            }
+            ErrorKind::StackOverflow => write!(f, "stack overflow"),
+            ErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            ErrorKind::MemoryOutOfBounds(address) => {
+                write!(f, "memory access out of bounds at {:#X}", address)
+            }
+            ErrorKind::WriteProtected(address) => {
+                write!(f, "write to read-only memory at {:#X}", address)
+            }
+            ErrorKind::PcOutOfBounds => write!(f, "PC out of bounds"),
+            ErrorKind::RomTooLarge { size, capacity } => write!(
+                f,
+                "ROM of {} bytes does not fit in {} bytes of program memory",
+                size, capacity
+            ),
+            ErrorKind::UnknownPlatform(name) => write!(f, "unknown platform {:?}", name),
+            ErrorKind::InvalidConfig(reason) => write!(f, "invalid configuration: {}", reason),
+            ErrorKind::UnknownImageFormat(path) => {
+                write!(f, "{} is not a .png, .pbm or .pgm file", path)
+            }
+            ErrorKind::GifTooLarge { width, height } => write!(
+                f,
+                "{}x{} is too large for a GIF, which is at most 65535 pixels either way",
+                width, height
+            ),
+            ErrorKind::UnknownVideoFormat(path) => {
+                write!(f, "{} is not a .gif or .y4m file", path)
+            }
+            ErrorKind::MachineStopped => write!(f, "the machine has stopped running"),
+            ErrorKind::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(opcode) = self.opcode {
+            write!(f, " (opcode {:04X})", opcode)?;
+        }
+        if let Some(pc) = self.pc {
+            write!(f, " at PC {:#05X}", pc)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
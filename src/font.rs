@@ -0,0 +1,176 @@
+pub const FONT_OFFSET: u16 = 0x50;
+pub const GLYPH_SIZE: u16 = 5;
+pub const GLYPH_COUNT: usize = 16;
+pub const LARGE_GLYPH_SIZE: u16 = 10;
+// Glyph addresses are 16 bits, both sets of glyphs have to end by here.
+pub const FONT_ADDRESS_LIMIT: usize = 0x1_0000;
+
+// The usual 4x5 hexadecimal digits, one byte per row, left aligned.
+pub const STANDARD_GLYPHS: [u8; GLYPH_COUNT * 5] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// The glyphs as they were stored in the COSMAC VIP interpreter ROM.
+pub const VIP_GLYPHS: [u8; GLYPH_COUNT * 5] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// The 8x10 SUPER-CHIP digits, extended with A to F the way Octo does.
+pub const LARGE_GLYPHS: [u8; GLYPH_COUNT * 10] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/**
+ * A hexadecimal font and the address it is loaded at.
+ * Interpreters disagree on both, so the machine takes whichever one the
+ * ROM expects and Fx29 resolves glyph addresses through it.
+ * The large SUPER-CHIP glyphs used by Fx30 are placed right after the small
+ * ones unless given a location of their own.
+ * Nothing stops a font from being placed too close to the top of the
+ * address space here, `MachineConfig::validate` rejects those.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Font {
+    offset: u16,
+    glyphs: [u8; GLYPH_COUNT * 5],
+    large_offset: u16,
+    large_glyphs: [u8; GLYPH_COUNT * 10],
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::new(FONT_OFFSET, STANDARD_GLYPHS)
+    }
+}
+
+impl Font {
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn new(offset: u16, glyphs: [u8; GLYPH_COUNT * 5]) -> Self {
+        Self {
+            offset,
+            glyphs,
+            large_offset: offset.wrapping_add(glyphs.len() as u16),
+            large_glyphs: LARGE_GLYPHS,
+        }
+    }
+
+    pub const fn with_large_glyphs(mut self, offset: u16, glyphs: [u8; GLYPH_COUNT * 10]) -> Self {
+        self.large_offset = offset;
+        self.large_glyphs = glyphs;
+        self
+    }
+
+    pub fn offset(&self) -> u16 {
+        self.offset
+    }
+
+    pub fn glyphs(&self) -> &[u8] {
+        &self.glyphs
+    }
+
+    pub fn large_offset(&self) -> u16 {
+        self.large_offset
+    }
+
+    pub fn large_glyphs(&self) -> &[u8] {
+        &self.large_glyphs
+    }
+
+    // One past the last byte of whichever set of glyphs ends last.
+    pub fn end(&self) -> usize {
+        (usize::from(self.offset) + self.glyphs.len())
+            .max(usize::from(self.large_offset) + self.large_glyphs.len())
+    }
+
+    // Address of the glyph for the low nibble of `digit`.
+    pub fn glyph_address(&self, digit: u8) -> u16 {
+        self.offset
+            .wrapping_add(u16::from(digit & 0xF) * GLYPH_SIZE)
+    }
+
+    pub fn large_glyph_address(&self, digit: u8) -> u16 {
+        self.large_offset
+            .wrapping_add(u16::from(digit & 0xF) * LARGE_GLYPH_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyph_address() {
+        let font = Font::default();
+        assert_eq!(font.glyph_address(0x0), FONT_OFFSET);
+        assert_eq!(font.glyph_address(0xA), FONT_OFFSET + 50);
+        // only the low nibble selects the glyph
+        assert_eq!(font.glyph_address(0x1F), FONT_OFFSET + 75);
+
+        let font = Font::new(0, VIP_GLYPHS);
+        assert_eq!(font.glyph_address(0x2), 10);
+    }
+
+    #[test]
+    fn test_large_glyph_address() {
+        let font = Font::default();
+        assert_eq!(font.large_glyph_address(0x0), FONT_OFFSET + 80);
+        assert_eq!(font.large_glyph_address(0x3), FONT_OFFSET + 110);
+
+        let font = Font::default().with_large_glyphs(0x100, LARGE_GLYPHS);
+        assert_eq!(font.large_glyph_address(0x1), 0x10A);
+    }
+
+    #[test]
+    fn test_end() {
+        assert_eq!(Font::default().end(), usize::from(FONT_OFFSET) + 240);
+        // the large glyphs would start past 0xFFFF, which is caught by `end`
+        let font = Font::new(0xFFC0, STANDARD_GLYPHS);
+        assert_eq!(font.large_offset(), 0x0010);
+        assert!(font.end() > FONT_ADDRESS_LIMIT);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::colors::{BACKGROUND_PALETTE, FOREGROUND_PALETTE};
+use crate::core::Machine;
+use crate::instructions::{InstructionParser, InstructionSet};
+
+// Colours are 0xRRGGBB.
+pub const BLACK: u32 = 0x00_0000;
+pub const WHITE: u32 = 0xFF_FFFF;
+
+/**
+ * The colours the four plane combinations of the display are shown in:
+ * off, first plane, second plane and both planes. Monochrome programs only
+ * use the first two.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [u32; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(BLACK, WHITE)
+    }
+}
+
+impl Palette {
+    // Off and on colours, the XO-CHIP planes get shades in between.
+    pub fn new(off: u32, on: u32) -> Self {
+        Self {
+            colors: [off, on, mix(off, on, 2), mix(off, on, 1)],
+        }
+    }
+}
+
+// A colour `parts` thirds of the way from `from` to `to`.
+fn mix(from: u32, to: u32, parts: u32) -> u32 {
+    [16, 8, 0].iter().fold(0, |color, shift| {
+        let (a, b) = ((from >> shift) & 0xFF, (to >> shift) & 0xFF);
+        color | ((a * (3 - parts) + b * parts) / 3) << shift
+    })
+}
+
+/**
+ * A snapshot of what the machine shows, one RGB colour per pixel, row by
+ * row. It takes care of the MegaChip framebuffer and the CHIP-8X colour
+ * board, so frontends only need to deal with colours.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Frame {
+    pub fn capture<T: InstructionParser>(machine: &Machine<T>, palette: &Palette) -> Self {
+        if machine.is_mega_mode() {
+            let display = machine.mega_display();
+            return Self {
+                width: display.width(),
+                height: display.height(),
+                pixels: display.pixels().iter().map(|argb| argb & WHITE).collect(),
+            };
+        }
+        let display = machine.display();
+        let (width, height) = (display.width(), display.height());
+        let chip8x = machine.instruction_set() == InstructionSet::Chip8X;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let color = display.color(x, y);
+                pixels.push(if chip8x {
+                    let colors = machine.colors();
+                    let (r, g, b) = if color == 0 {
+                        BACKGROUND_PALETTE[usize::from(colors.background())]
+                    } else {
+                        FOREGROUND_PALETTE[usize::from(colors.foreground(x, y))]
+                    };
+                    u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b)
+                } else {
+                    palette.colors[usize::from(color & 3)]
+                });
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixels[y * self.width + x]
+    }
+
+    // Nearest neighbour scaling to any size.
+    pub fn resized(&self, width: usize, height: usize) -> Frame {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(self.pixel(x * self.width / width, y * self.height / height));
+            }
+        }
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Platform;
+
+    #[test]
+    fn test_palette() {
+        let palette = Palette::new(0x00_0000, 0xFF_FFFF);
+        assert_eq!(palette.colors, [0x00_0000, 0xFF_FFFF, 0xAA_AAAA, 0x55_5555]);
+    }
+
+    #[test]
+    fn test_capture() {
+        let mut machine = Machine::builder("TestVM").build().unwrap();
+        // LD I, 0x50; DRW V0, V0, 1: the top row of the 0 glyph
+        machine.load_rom_bytes(&[0xA0, 0x50, 0xD0, 0x01]).unwrap();
+        machine.step().unwrap();
+        machine.step().unwrap();
+        let frame = Frame::capture(&machine, &Palette::new(0x10_2030, 0xF0_E0D0));
+        assert_eq!((frame.width, frame.height), (64, 32));
+        assert_eq!(frame.pixel(3, 0), 0xF0_E0D0);
+        assert_eq!(frame.pixel(4, 0), 0x10_2030);
+
+        let mut machine = Machine::builder("TestVM")
+            .platform(Platform::Chip8X)
+            .build()
+            .unwrap();
+        machine.load_rom_bytes(&[0xA0, 0x50, 0xD0, 0x01]).unwrap();
+        machine.step().unwrap();
+        machine.step().unwrap();
+        let frame = Frame::capture(&machine, &Palette::default());
+        // red on the default blue background
+        assert_eq!(frame.pixel(0, 0), 0xFF_0000);
+        assert_eq!(frame.pixel(4, 0), 0x00_0080);
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt::Write;
+
+use crate::core::Machine;
+use crate::error::{Error, Result};
+use crate::instructions::InstructionParser;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// How long a headless run goes on for, unless the program exits first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunLimit {
+    Cycles(u64),
+    Frames(u64),
+}
+
+/**
+ * Run a machine without a frontend.
+ * `Cycles` counts instructions, ticking the timers every `cycles_per_frame`
+ * of them so timing matches a frame based run; `Frames` runs whole 60 Hz
+ * frames. Runs as fast as possible rather than in real time.
+ */
+pub fn run<T: InstructionParser>(machine: &mut Machine<T>, limit: RunLimit) -> Result<()> {
+    run_with(machine, limit, |_, _| Ok(()))
+}
+
+/**
+ * Like `run`, but `on_frame` is called with the number of frames run so far
+ * before the first one and after every timer tick, e.g. to take screenshots.
+ */
+pub fn run_with<T, F>(machine: &mut Machine<T>, limit: RunLimit, mut on_frame: F) -> Result<()>
+where
+    T: InstructionParser,
+    F: FnMut(&Machine<T>, u64) -> Result<()>,
+{
+    on_frame(machine, 0)?;
+    match limit {
+        RunLimit::Cycles(cycles) => {
+            let per_frame = u64::from(machine.cycles_per_frame().max(1));
+            for cycle in 1..=cycles {
+                if machine.is_halted() {
+                    break;
+                }
+                machine.step()?;
+                if cycle % per_frame == 0 {
+                    machine.tick_timers();
+                    on_frame(machine, cycle / per_frame)?;
+                }
+            }
+        }
+        RunLimit::Frames(frames) => {
+            for frame in 1..=frames {
+                if machine.is_halted() {
+                    break;
+                }
+                machine.run_frame()?;
+                on_frame(machine, frame)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// 64-bit FNV-1a, stable across platforms and releases unlike `Hash`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/**
+ * The machine state at the end of a headless run, meant to be compared
+ * between runs. The framebuffer is one hex string per row, 8 pixels to a
+ * byte, most significant bit leftmost, set for pixels lit on any plane.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineState {
+    pub pc: u16,
+    pub i: u32,
+    pub v: Vec<u8>,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub halted: bool,
+    pub width: usize,
+    pub height: usize,
+    pub framebuffer: Vec<String>,
+    pub memory_hash: u64,
+    pub error: Option<String>,
+}
+
+impl MachineState {
+    pub fn capture<T: InstructionParser>(machine: &Machine<T>, error: Option<&Error>) -> Self {
+        let display = machine.display();
+        let framebuffer = (0..display.height())
+            .map(|y| {
+                let mut row = String::new();
+                for byte_x in (0..display.width()).step_by(8) {
+                    let byte = (0..8)
+                        .filter(|bit| byte_x + bit < display.width())
+                        .filter(|bit| display.pixel(byte_x + bit, y))
+                        .fold(0u8, |byte, bit| byte | 0x80 >> bit);
+                    let _ = write!(row, "{:02x}", byte);
+                }
+                row
+            })
+            .collect();
+        Self {
+            pc: machine.pc(),
+            i: machine.index_register(),
+            v: machine.registers().to_vec(),
+            stack: machine.stack().to_vec(),
+            delay_timer: machine.timers().delay(),
+            sound_timer: machine.timers().sound(),
+            halted: machine.is_halted(),
+            width: display.width(),
+            height: display.height(),
+            framebuffer,
+            memory_hash: fnv1a(machine.memory()),
+            error: error.map(|e| e.to_string()),
+        }
+    }
+
+    // Keys always come out in the same order so the output can be diffed.
+    pub fn to_json(&self) -> String {
+        let list = |items: Vec<String>| items.join(", ");
+        let rows: Vec<String> = self
+            .framebuffer
+            .iter()
+            .map(|row| format!("      \"{}\"", row))
+            .collect();
+        format!(
+            concat!(
+                "{{\n",
+                "  \"pc\": {},\n",
+                "  \"i\": {},\n",
+                "  \"v\": [{}],\n",
+                "  \"stack\": [{}],\n",
+                "  \"delay_timer\": {},\n",
+                "  \"sound_timer\": {},\n",
+                "  \"halted\": {},\n",
+                "  \"display\": {{\n",
+                "    \"width\": {},\n",
+                "    \"height\": {},\n",
+                "    \"rows\": [\n{}\n    ]\n",
+                "  }},\n",
+                "  \"memory_hash\": \"{:016x}\",\n",
+                "  \"error\": {}\n",
+                "}}"
+            ),
+            self.pc,
+            self.i,
+            list(self.v.iter().map(u8::to_string).collect()),
+            list(self.stack.iter().map(u16::to_string).collect()),
+            self.delay_timer,
+            self.sound_timer,
+            self.halted,
+            self.width,
+            self.height,
+            rows.join(",\n"),
+            self.memory_hash,
+            self.error
+                .as_ref()
+                .map_or_else(|| "null".to_string(), |e| json_string(e)),
+        )
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_run_and_capture() {
+        let mut machine = Machine::builder("TestVM").build().unwrap();
+        // LD I, 0x50; DRW V0, V0, 5; LD V3, 0x2A; LD DT, V3; JP 0x208
+        machine
+            .load_rom_bytes(&[0xA0, 0x50, 0xD0, 0x05, 0x63, 0x2A, 0xF3, 0x15, 0x12, 0x08])
+            .unwrap();
+        run(&mut machine, RunLimit::Cycles(20)).unwrap();
+        let state = MachineState::capture(&machine, None);
+        assert_eq!(state.pc, 0x208);
+        assert_eq!(state.i, 0x50);
+        assert_eq!(state.v[3], 0x2A);
+        // 20 cycles at 10 per frame is two timer ticks
+        assert_eq!(state.delay_timer, 0x28);
+        assert_eq!(state.framebuffer.len(), 32);
+        assert_eq!(state.framebuffer[0], "f000000000000000");
+        assert_eq!(state.framebuffer[1], "9000000000000000");
+
+        let json = state.to_json();
+        assert!(json.contains("\"pc\": 520,"));
+        assert!(json.contains("\"v\": [0, 0, 0, 42, 0,"));
+        assert!(json.contains("\"stack\": [],"));
+        assert!(json.contains("\"error\": null"));
+
+        let mut frames = Vec::new();
+        run_with(&mut machine, RunLimit::Frames(2), |machine, frame| {
+            frames.push((frame, machine.timers().delay()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(frames, vec![(0, 0x28), (1, 0x27), (2, 0x26)]);
+    }
+
+    #[test]
+    fn test_error_is_escaped() {
+        assert_eq!(json_string("a \"b\"\n"), "\"a \\\"b\\\"\\n\"");
+    }
+}
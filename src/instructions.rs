@@ -1,10 +1,13 @@
+use crate::error::Error;
+
 type Address = u16;
 type Register = u8;
 type Data = u8;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum Instruction {
-    ClearScreen,                              // 00E0 - CLS
+    ClearScreen,                              // 00E0 - CLS, 0230 - CLS (VIP hi-res)
     Return,                                   // 00EE - RET
     SYS,                                      // 0nnn - SYS addr
     Jump(Address),                            // 1nnn - JP addr
@@ -20,9 +23,9 @@ pub enum Instruction {
     Xor(Register, Register),                  // 8xy3 - XOR Vx, Vy
     AddRegister(Register, Register),          // 8xy4 - ADD Vx, Vy
     SubRegister(Register, Register),          // 8xy5 - SUB Vx, Vy
-    ShiftRight(Register),                     // 8xy6 - SHR Vx
+    ShiftRight(Register, Register),           // 8xy6 - SHR Vx {, Vy}
     SubNRegister(Register, Register),         // 8xy7 - SUBN Vx, Vy
-    ShiftLeft(Register),                      // 8xyE - SHL Vx
+    ShiftLeft(Register, Register),            // 8xyE - SHL Vx {, Vy}
     SkipNotEqualRegister(Register, Register), // 9xy0 - SNE Vx, Vy
     LoadImmediate(Address),                   // Annn - LD I, addr
     JumpBase(Address),                        // Bnnn - JP V0, address
@@ -39,8 +42,82 @@ pub enum Instruction {
     LoadIBCD(Register),                       // Fx33 - LD B, Vx
     StoreRegisters(Register),                 // Fx55 - LD [I], Vx
     LoadRegisters(Register),                  // Fx65 - LD Vx, [I]
+    ScrollDown(u8),                           // 00Cn - SCD nibble (SUPER-CHIP)
+    ScrollRight,                              // 00FB - SCR (SUPER-CHIP)
+    ScrollLeft,                               // 00FC - SCL (SUPER-CHIP)
+    Exit,                                     // 00FD - EXIT (SUPER-CHIP), 00ED - STOP (CHIP-8E)
+    LowResolution,                            // 00FE - LOW (SUPER-CHIP)
+    HighResolution,                           // 00FF - HIGH (SUPER-CHIP)
+    DisplayLargeSprite(Register, Register),   // Dxy0 - DRW Vx, Vy, 0 (SUPER-CHIP)
+    LoadLargeFontSprite(Register),            // Fx30 - LD HF, Vx (SUPER-CHIP)
+    StoreFlags(Register),                     // Fx75 - LD R, Vx (SUPER-CHIP)
+    LoadFlags(Register),                      // Fx85 - LD Vx, R (SUPER-CHIP)
+    ScrollUp(u8),                             // 00Dn - SCU nibble (XO-CHIP), 00Bn (MegaChip)
+    SaveRange(Register, Register),            // 5xy2 - SAVE Vx - Vy (XO-CHIP, CHIP-8E)
+    LoadRange(Register, Register),            // 5xy3 - LOAD Vx - Vy (XO-CHIP, CHIP-8E)
+    LoadLongImmediate,                        // F000 nnnn - LD I, long addr (XO-CHIP)
+    SelectPlanes(u8),                         // Fn01 - PLANE nibble (XO-CHIP)
+    LoadAudio,                                // F002 - AUDIO (XO-CHIP)
+    LoadPitch(Register),                      // Fx3A - PITCH Vx (XO-CHIP)
+    CycleBackground,                          // 02A0 - BGC (CHIP-8X)
+    AddNibbles(Register, Register),           // 5xy1 - ADD Vx, Vy nibble by nibble (CHIP-8X)
+    SetColorZones(Register, Register),        // Bxy0 - COL Vx, Vy (CHIP-8X)
+    SetColorRows(Register, Register, u8),     // Bxyn - COL Vx, Vy, nibble (CHIP-8X)
+    SkipSecondKeyPress(Register),             // ExF2 - SKP2 Vx (CHIP-8X)
+    SkipNotSecondKeyPress(Register),          // ExF5 - SKNP2 Vx (CHIP-8X)
+    WaitDelay,                                // 0151 - WAIT DT (CHIP-8E)
+    Skip,                                     // 0188 - SKIP (CHIP-8E)
+    SkipGreater(Register, Register),          // 5xy1 - SGT Vx, Vy (CHIP-8E)
+    JumpBack(u8),                             // BBnn - JB byte (CHIP-8E)
+    JumpForward(u8),                          // BFnn - JF byte (CHIP-8E)
+    OutputPort(Register),                     // Fx03 - OUT Vx (CHIP-8E)
+    SkipBytes(Register),                      // Fx1B - SKIP Vx (CHIP-8E)
+    LoadDelayAndWait(Register),               // Fx4F - LD DT, Vx and WAIT DT (CHIP-8E)
+    MegaOff,                                  // 0010 - MEGAOFF (MegaChip)
+    MegaOn,                                   // 0011 - MEGAON (MegaChip)
+    LoadMegaImmediate(u8),                    // 01nn nnnn - LDHI I, long addr (MegaChip)
+    LoadPalette(u8),                          // 02nn - LDPAL byte (MegaChip)
+    SpriteWidth(u8),                          // 03nn - SPRW byte (MegaChip)
+    SpriteHeight(u8),                         // 04nn - SPRH byte (MegaChip)
+    ScreenAlpha(u8),                          // 05nn - ALPHA byte (MegaChip)
+    PlaySample(u8),                           // 060n - DIGISND nibble (MegaChip)
+    StopSample,                               // 0700 - STOPSND (MegaChip)
+    BlendMode(u8),                            // 080n - BMODE nibble (MegaChip)
+    CollisionColor(u8),                       // 09nn - CCOL byte (MegaChip)
+}
+
+// Which opcodes a parser decodes on top of the base CHIP-8 instructions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionSet {
+    #[default]
+    Chip8,
+    SuperChip,
+    XoChip, // SUPER-CHIP plus the XO-CHIP extensions
+    Chip8X,
+    Chip8E,
+    MegaChip, // SUPER-CHIP plus the MegaChip extensions
 }
 
+impl InstructionSet {
+    pub fn includes_super_chip(self) -> bool {
+        matches!(
+            self,
+            InstructionSet::SuperChip | InstructionSet::XoChip | InstructionSet::MegaChip
+        )
+    }
+}
+
+/**
+ * Decodes a single opcode.
+ * The address of a long load (F000 nnnn, 01nn nnnn) continues in the word
+ * after the opcode, so the machine reads it while executing the instruction.
+*/
 pub trait InstructionParser {
-    fn try_from(&self, opcode: u16) -> Result<Instruction, String>;
+    fn try_from(&self, opcode: u16) -> Result<Instruction, Error>;
+
+    // The machine sizes its memory to match, XO-CHIP programs get 64 KiB
+    // and MegaChip ones 32 MiB.
+    fn instruction_set(&self) -> InstructionSet {
+        InstructionSet::Chip8
+    }
 }
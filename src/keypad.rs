@@ -0,0 +1,62 @@
+pub const KEY_COUNT: usize = 16;
+
+/**
+ * State of the 16-key hexadecimal keypad.
+ * Keys are addressed by their hex value, 0x0 to 0xF.
+ * Anything out of range is ignored rather than panicking since the key
+ * usually comes straight out of a register the ROM controls.
+*/
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Keypad {
+    keys: [bool; KEY_COUNT],
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn press(&mut self, key: u8) {
+        if let Some(state) = self.keys.get_mut(usize::from(key)) {
+            *state = true;
+        }
+    }
+
+    pub fn release(&mut self, key: u8) {
+        if let Some(state) = self.keys.get_mut(usize::from(key)) {
+            *state = false;
+        }
+    }
+
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.keys.get(usize::from(key)).copied().unwrap_or(false)
+    }
+
+    pub fn reset(&mut self) {
+        self.keys = [false; KEY_COUNT];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_press_release() {
+        let mut keypad = Keypad::new();
+        assert!(!keypad.is_pressed(0xA));
+        keypad.press(0xA);
+        assert!(keypad.is_pressed(0xA));
+        assert!(!keypad.is_pressed(0xB));
+        keypad.release(0xA);
+        assert!(!keypad.is_pressed(0xA));
+    }
+
+    #[test]
+    fn test_out_of_range_keys() {
+        let mut keypad = Keypad::new();
+        keypad.press(0x10);
+        assert!(!keypad.is_pressed(0x10));
+        assert_eq!(keypad, Keypad::new());
+    }
+}
@@ -0,0 +1,46 @@
+#[macro_use]
+extern crate log;
+extern crate rand;
+
+use crate::control::MachineHandle;
+use crate::instructions::InstructionParser;
+
+pub mod audio;
+mod bitmasks;
+pub mod colors;
+pub mod control;
+pub mod core;
+pub mod display;
+pub mod error;
+pub mod font;
+pub mod frame;
+pub mod headless;
+pub mod instructions;
+pub mod keypad;
+pub mod megachip;
+pub mod opcodes;
+pub mod opcodesv2;
+mod ophandlers;
+pub mod platform;
+pub mod quirks;
+pub mod recorder;
+pub mod screenshot;
+pub mod terminal;
+pub mod timers;
+
+/**
+ * Start the machine in a separate thread.
+ * We do this because we need to be able to parse instructions in one
+ * thread and render the output in another. Otherwise we will block on
+ * each instruction while doing the rendering. The handle is how the other
+ * thread drives the machine and hears back from it, `rom` is what the
+ * machine was loaded with so it can be reloaded on reset.
+*/
+pub fn launch_thread<T>(machine: core::Machine<T>, rom: Vec<u8>) -> MachineHandle
+where
+    T: InstructionParser,
+    T: std::marker::Send,
+    T: 'static,
+{
+    MachineHandle::spawn(machine, rom)
+}
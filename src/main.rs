@@ -1,55 +1,182 @@
 #[macro_use]
 extern crate log;
 extern crate env_logger;
-extern crate rand;
 
-use crate::instructions::InstructionParser;
-use std::convert::TryInto;
+use chip8::audio::{AudioSink, Buzzer, WavSink};
+use chip8::core;
+use chip8::frame::Palette;
+use chip8::headless::{self, MachineState, RunLimit};
+use chip8::platform::Platform;
+use chip8::recorder::Recorder;
+use chip8::screenshot::ScreenshotOptions;
+use chip8::terminal::TerminalFrontend;
 use std::env;
-use std::ops::Deref;
-use std::thread::JoinHandle;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
 
-mod bitmasks;
-mod core;
-mod instructions;
-mod opcodes;
-mod opcodesv2;
-mod ophandlers;
+fn usage() -> ! {
+    let platforms: Vec<&str> = Platform::ALL.iter().map(|p| p.name()).collect();
+    eprintln!("Usage: chip8 [run] [--platform NAME] [--headless (--cycles N | --frames N)]");
+    eprintln!("             [--screenshot-at FRAME FILE] [--record FILE] [--wav FILE]");
+    eprintln!("             [--scale N] [--colors OFF,ON] ROM");
+    eprintln!("Platforms: {} (default: chip8)", platforms.join(", "));
+    eprintln!("--headless runs without a frontend and prints the final state as JSON");
+    eprintln!("Screenshots are .png, .pbm or .pgm files, recordings .gif or .y4m");
+    eprintln!("Colours are RRGGBB in hex, F9 starts and stops recording in the terminal");
+    eprintln!("--wav writes the buzzer to a 16-bit 44.1 kHz WAV file");
+    process::exit(2);
+}
+
+struct Options {
+    platform: Platform,
+    rom_file: String,
+    headless: bool,
+    limit: Option<RunLimit>,
+    screenshot: Option<(u64, PathBuf)>,
+    record: Option<PathBuf>,
+    wav: Option<PathBuf>,
+    screenshot_options: ScreenshotOptions,
+}
 
-/**
- * Start the machine in a separate thread.
- * We do this because we need to be able to parse instructions in one
- * thread and render the output in another. Otherwise we will block on
- * each instruction while doing the rendering.
-*/
-pub fn launch_thread<T>(
-    mut machine: core::Machine<T>,
-) -> JoinHandle<std::result::Result<(), String>>
+fn parse_value<V: std::str::FromStr>(value: Option<String>) -> V
 where
-    T: InstructionParser,
-    T: std::marker::Send,
-    T: 'static,
+    V::Err: std::fmt::Display,
 {
-    std::thread::spawn(move || {
-        debug!(
-            "Inside the spawned thread: {:?}",
-            std::thread::current().id()
-        );
-        machine.start()
+    let value = value.unwrap_or_else(|| usage());
+    value.parse().unwrap_or_else(|e| {
+        eprintln!("{}: {}", value, e);
+        usage()
     })
 }
 
+fn parse_colors(value: Option<String>) -> Palette {
+    let value = value.unwrap_or_else(|| usage());
+    let colors: Vec<u32> = value
+        .split(',')
+        .map(|color| u32::from_str_radix(color.trim_start_matches('#'), 16))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| {
+            eprintln!("{}: {}", value, e);
+            usage()
+        });
+    match colors[..] {
+        [off, on] if off <= 0xFF_FFFF && on <= 0xFF_FFFF => Palette::new(off, on),
+        _ => usage(),
+    }
+}
+
+fn parse_args() -> Options {
+    let mut platform = Platform::default();
+    let mut rom_file = None;
+    let mut headless = false;
+    let mut limit = None;
+    let mut screenshot = None;
+    let mut record = None;
+    let mut wav = None;
+    let mut screenshot_options = ScreenshotOptions::default();
+    let mut args = env::args().skip(1).peekable();
+    // `run` is what happens anyway, it can be left out.
+    if args.peek().map(String::as_str) == Some("run") {
+        args.next();
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" | "-p" => platform = parse_value(args.next()),
+            "--headless" => headless = true,
+            "--cycles" => limit = Some(RunLimit::Cycles(parse_value(args.next()))),
+            "--frames" => limit = Some(RunLimit::Frames(parse_value(args.next()))),
+            "--screenshot-at" => {
+                let frame = parse_value(args.next());
+                let path = args.next().unwrap_or_else(|| usage());
+                screenshot = Some((frame, PathBuf::from(path)));
+            }
+            "--record" => record = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--wav" => wav = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--scale" => screenshot_options.scale = parse_value(args.next()),
+            "--colors" => screenshot_options.palette = parse_colors(args.next()),
+            "--help" | "-h" => usage(),
+            _ if rom_file.is_none() => rom_file = Some(arg),
+            _ => usage(),
+        }
+    }
+    if headless != limit.is_some() {
+        usage();
+    }
+    Options {
+        platform,
+        rom_file: rom_file.unwrap_or_else(|| usage()),
+        headless,
+        limit,
+        screenshot,
+        record,
+        wav,
+        screenshot_options,
+    }
+}
+
 fn main() {
     env_logger::init();
-    let rom_file = env::args().nth(1).expect("Please input a ROM file");
-    let ins_parser = opcodes::OpcodeMaskParser {};
-    let mut vm = core::Machine::new("Chip8", ins_parser);
-    vm.load_rom(&rom_file)
-        .expect("Unable to load ROM from file");
+    let options = parse_args();
+    let mut vm = core::Machine::builder("Chip8")
+        .platform(options.platform)
+        .build()
+        .expect("Unable to set up the machine");
+    let rom = fs::read(&options.rom_file).expect("Unable to load ROM from file");
+    let summary = vm.load_rom_bytes(&rom).expect("Unable to load ROM");
+    info!(
+        "Loaded {} bytes at {:#X}..{:#X} for {}",
+        summary.bytes_loaded, summary.start_address, summary.end_address, options.platform
+    );
     debug!("{:#?}", vm);
-    let handle = launch_thread(vm);
-    match handle.join() {
-        Ok(_) => info!("Shutting down..."),
-        Err(e) => error!("VM thread exited with error {:?}", e),
+    if let (true, Some(limit)) = (options.headless, options.limit) {
+        let screenshot = options.screenshot;
+        let screenshot_options = options.screenshot_options;
+        let mut recorder = options.record.map(|path| {
+            Recorder::create(path, screenshot_options).expect("Unable to start recording")
+        });
+        let mut buzzer = Buzzer::new();
+        let mut wav = options
+            .wav
+            .map(|path| WavSink::create(path).expect("Unable to create WAV file"));
+        let result = headless::run_with(&mut vm, limit, |vm, frame| {
+            if let (Some(recorder), true) = (recorder.as_mut(), frame > 0) {
+                recorder.record(vm)?;
+            }
+            if let (Some(wav), true) = (wav.as_mut(), frame > 0) {
+                buzzer.play(vm, wav)?;
+            }
+            match &screenshot {
+                Some((at, path)) if *at == frame => vm.save_screenshot(path, &screenshot_options),
+                _ => Ok(()),
+            }
+        });
+        let result = result
+            .and_then(|()| recorder.map_or(Ok(()), |mut r| r.finish()))
+            .and_then(|()| wav.map_or(Ok(()), |mut w| w.finish()));
+        let state = MachineState::capture(&vm, result.as_ref().err());
+        println!("{}", state.to_json());
+        if result.is_err() {
+            process::exit(1);
+        }
+        return;
+    }
+    let mut frontend = TerminalFrontend::new(vm, rom);
+    if let Some((frame, path)) = options.screenshot {
+        frontend.screenshot_at(frame, path, options.screenshot_options);
+    }
+    if let Some(path) = options.record {
+        frontend
+            .record_to(path, options.screenshot_options)
+            .expect("Unable to start recording");
+    }
+    if let Some(path) = options.wav {
+        frontend.play_to(Box::new(
+            WavSink::create(path).expect("Unable to create WAV file"),
+        ));
+    }
+    match frontend.run() {
+        Ok(()) => info!("Shutting down..."),
+        Err(e) => error!("VM halted: {}", e),
     }
 }
@@ -0,0 +1,305 @@
+pub const MEGA_WIDTH: usize = 256;
+pub const MEGA_HEIGHT: usize = 192;
+pub const PALETTE_SIZE: usize = 256;
+// Sample rate, length and a reserved byte precede the sample data.
+pub const SAMPLE_HEADER_SIZE: usize = 6;
+
+// How a sprite pixel is combined with the pixel already on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Alpha25,
+    Alpha50,
+    Alpha75,
+    Add,
+    Multiply,
+}
+
+impl BlendMode {
+    // 080n, unknown modes draw normally.
+    pub fn from_nibble(mode: u8) -> Self {
+        match mode {
+            1 => BlendMode::Alpha25,
+            2 => BlendMode::Alpha50,
+            3 => BlendMode::Alpha75,
+            4 => BlendMode::Add,
+            5 => BlendMode::Multiply,
+            _ => BlendMode::Normal,
+        }
+    }
+
+    fn blend(self, src: u32, dst: u32) -> u32 {
+        let channel = |color: u32, shift: u32| (color >> shift) & 0xFF;
+        let mix = |f: &dyn Fn(u32, u32) -> u32| {
+            [16, 8, 0].iter().fold(0xFF00_0000, |color, shift| {
+                color | f(channel(src, *shift), channel(dst, *shift)).min(0xFF) << shift
+            })
+        };
+        match self {
+            BlendMode::Normal => src,
+            BlendMode::Alpha25 => mix(&|s, d| (s + 3 * d) / 4),
+            BlendMode::Alpha50 => mix(&|s, d| (s + d) / 2),
+            BlendMode::Alpha75 => mix(&|s, d| (3 * s + d) / 4),
+            BlendMode::Add => mix(&|s, d| s + d),
+            BlendMode::Multiply => mix(&|s, d| s * d / 0xFF),
+        }
+    }
+}
+
+/**
+ * The 256x192 framebuffer of MegaChip mode.
+ * Pixels are 32-bit ARGB colours. Sprites are `sprite_width` by
+ * `sprite_height` bytes, each one an index into the palette loaded by 02nn
+ * with 0 being transparent. Instead of XOR-ing, sprite pixels are blended
+ * onto the screen; drawing over a pixel last drawn with the collision colour
+ * counts as a collision.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct MegaDisplay {
+    pixels: Vec<u32>,
+    indices: Vec<u8>, // palette index each pixel was last drawn with
+    palette: [u32; PALETTE_SIZE],
+    sprite_width: usize,
+    sprite_height: usize,
+    alpha: u8,
+    blend_mode: BlendMode,
+    collision_color: u8,
+}
+
+impl Default for MegaDisplay {
+    fn default() -> Self {
+        Self {
+            pixels: vec![0xFF00_0000; MEGA_WIDTH * MEGA_HEIGHT],
+            indices: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            palette: [0xFF00_0000; PALETTE_SIZE],
+            sprite_width: 0,
+            sprite_height: 0,
+            alpha: 0xFF,
+            blend_mode: BlendMode::Normal,
+            collision_color: 0,
+        }
+    }
+}
+
+impl MegaDisplay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn width(&self) -> usize {
+        MEGA_WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        MEGA_HEIGHT
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixels[y * MEGA_WIDTH + x]
+    }
+
+    pub fn palette(&self) -> &[u32; PALETTE_SIZE] {
+        &self.palette
+    }
+
+    // 02nn: colours are stored as 4 bytes each, ARGB, starting at index 1.
+    pub fn load_palette(&mut self, colors: &[u8]) {
+        for (entry, color) in self.palette[1..].iter_mut().zip(colors.chunks_exact(4)) {
+            *entry = u32::from_be_bytes([color[0], color[1], color[2], color[3]]);
+        }
+    }
+
+    // 03nn and 04nn, a size of 0 means 256.
+    pub fn set_sprite_width(&mut self, width: u8) {
+        self.sprite_width = usize::from(width);
+    }
+
+    pub fn set_sprite_height(&mut self, height: u8) {
+        self.sprite_height = usize::from(height);
+    }
+
+    // Number of bytes a sprite takes up in memory.
+    pub fn sprite_size(&self) -> usize {
+        self.sprite_width() * self.sprite_height()
+    }
+
+    fn sprite_width(&self) -> usize {
+        if self.sprite_width == 0 {
+            PALETTE_SIZE
+        } else {
+            self.sprite_width
+        }
+    }
+
+    fn sprite_height(&self) -> usize {
+        if self.sprite_height == 0 {
+            PALETTE_SIZE
+        } else {
+            self.sprite_height
+        }
+    }
+
+    // Opacity of the whole screen set by 05nn, for the frontend to apply.
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn set_collision_color(&mut self, index: u8) {
+        self.collision_color = index;
+    }
+
+    pub fn clear(&mut self) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = 0xFF00_0000;
+        }
+        for index in self.indices.iter_mut() {
+            *index = 0;
+        }
+    }
+
+    // Sprites are clipped at the screen edges. Returns true on a collision.
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let width = self.sprite_width();
+        let mut collision = false;
+        for (row, bytes) in sprite.chunks(width).enumerate() {
+            let py = y + row;
+            if py >= MEGA_HEIGHT {
+                break;
+            }
+            for (column, index) in bytes.iter().enumerate() {
+                let px = x + column;
+                if px >= MEGA_WIDTH {
+                    break;
+                }
+                if *index == 0 {
+                    continue;
+                }
+                let offset = py * MEGA_WIDTH + px;
+                let under = self.indices[offset];
+                collision |= under != 0 && under == self.collision_color;
+                let color = self.palette[usize::from(*index)];
+                self.pixels[offset] = self.blend_mode.blend(color, self.pixels[offset]);
+                self.indices[offset] = *index;
+            }
+        }
+        collision
+    }
+
+    // Move the screen contents by (dx, dy), blanking what is scrolled in.
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (MEGA_WIDTH as isize, MEGA_HEIGHT as isize);
+        let pixels = self.pixels.clone();
+        let indices = self.indices.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = (x - dx, y - dy);
+                let offset = (y * width + x) as usize;
+                if (0..width).contains(&sx) && (0..height).contains(&sy) {
+                    let source = (sy * width + sx) as usize;
+                    self.pixels[offset] = pixels[source];
+                    self.indices[offset] = indices[source];
+                } else {
+                    self.pixels[offset] = 0xFF00_0000;
+                    self.indices[offset] = 0;
+                }
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// A digitised sound started by 060n, 8-bit unsigned samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub rate: u16,
+    pub data: Vec<u8>,
+    pub looping: bool,
+}
+
+impl Sample {
+    // Parse the 6 byte header at the start of a sample.
+    pub fn header(header: &[u8; SAMPLE_HEADER_SIZE]) -> (u16, usize) {
+        let rate = u16::from_be_bytes([header[0], header[1]]);
+        let len =
+            usize::from(header[2]) << 16 | usize::from(header[3]) << 8 | usize::from(header[4]);
+        (rate, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_sprite() {
+        let mut display = MegaDisplay::new();
+        display.load_palette(&[0xFF, 0x10, 0x20, 0x30, 0xFF, 0x40, 0x50, 0x60]);
+        display.set_sprite_width(2);
+        display.set_sprite_height(2);
+        assert_eq!(display.sprite_size(), 4);
+        display.set_collision_color(1);
+
+        assert!(!display.draw_sprite(10, 20, &[1, 0, 0, 2]));
+        assert_eq!(display.pixel(10, 20), 0xFF10_2030);
+        assert_eq!(display.pixel(11, 20), 0xFF00_0000);
+        assert_eq!(display.pixel(11, 21), 0xFF40_5060);
+
+        // drawing over the collision colour reports it, transparent pixels don't
+        assert!(!display.draw_sprite(9, 20, &[0, 0, 0, 0]));
+        assert!(display.draw_sprite(10, 20, &[2, 0, 0, 0]));
+        assert_eq!(display.pixel(10, 20), 0xFF40_5060);
+
+        // clipped at the right edge
+        display.draw_sprite(255, 0, &[1, 1, 1, 1]);
+        assert_eq!(display.pixel(255, 1), 0xFF10_2030);
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let src = 0xFF80_4020;
+        let dst = 0xFF40_4040;
+        assert_eq!(BlendMode::Normal.blend(src, dst), src);
+        assert_eq!(BlendMode::Alpha50.blend(src, dst), 0xFF60_4030);
+        assert_eq!(BlendMode::Add.blend(src, dst), 0xFFC0_8060);
+        assert_eq!(BlendMode::Add.blend(0xFFFF_0000, dst), 0xFFFF_4040);
+        assert_eq!(BlendMode::from_nibble(9), BlendMode::Normal);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut display = MegaDisplay::new();
+        display.load_palette(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        display.set_sprite_width(1);
+        display.set_sprite_height(1);
+        display.draw_sprite(0, 0, &[1]);
+        display.scroll(4, 2);
+        assert_eq!(display.pixel(4, 2), 0xFFFF_FFFF);
+        assert_eq!(display.pixel(0, 0), 0xFF00_0000);
+    }
+
+    #[test]
+    fn test_sample_header() {
+        let header = [0x1F, 0x40, 0x01, 0x00, 0x02, 0x00];
+        assert_eq!(Sample::header(&header), (8000, 0x10002));
+    }
+}
@@ -1,12 +1,182 @@
 use crate::bitmasks::*;
-use crate::instructions::{Instruction, InstructionParser};
+use crate::error::Error;
+use crate::instructions::{Instruction, InstructionParser, InstructionSet};
 
-#[allow(dead_code)]
-pub struct OpcodeMaskParser {}
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OpcodeMaskParser {
+    instruction_set: InstructionSet,
+}
+
+impl OpcodeMaskParser {
+    pub fn new(instruction_set: InstructionSet) -> Self {
+        Self { instruction_set }
+    }
+
+    // SUPER-CHIP opcodes. Most of them would otherwise decode as SYS.
+    fn try_from_super_chip(opcode: u16) -> Option<Instruction> {
+        match mask_F000(opcode) {
+            0x0 if mask_0F00(opcode) == 0 => match mask_00FF(opcode) {
+                0xC0..=0xCF => Some(Instruction::ScrollDown(mask_000F(opcode))),
+                0xFB => Some(Instruction::ScrollRight),
+                0xFC => Some(Instruction::ScrollLeft),
+                0xFD => Some(Instruction::Exit),
+                0xFE => Some(Instruction::LowResolution),
+                0xFF => Some(Instruction::HighResolution),
+                _ => None,
+            },
+            0xD if mask_000F(opcode) == 0 => Some(Instruction::DisplayLargeSprite(
+                mask_0F00(opcode),
+                mask_00F0(opcode),
+            )),
+            0xF => {
+                let register = mask_0F00(opcode);
+                match mask_00FF(opcode) {
+                    0x30 => Some(Instruction::LoadLargeFontSprite(register)),
+                    0x75 => Some(Instruction::StoreFlags(register)),
+                    0x85 => Some(Instruction::LoadFlags(register)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // XO-CHIP opcodes, decoded ahead of the SUPER-CHIP ones.
+    fn try_from_xo_chip(opcode: u16) -> Option<Instruction> {
+        let r1 = mask_0F00(opcode);
+        let r2 = mask_00F0(opcode);
+        match mask_F000(opcode) {
+            0x0 if r1 == 0 && r2 == 0xD => Some(Instruction::ScrollUp(mask_000F(opcode))),
+            0x5 => match mask_000F(opcode) {
+                0x2 => Some(Instruction::SaveRange(r1, r2)),
+                0x3 => Some(Instruction::LoadRange(r1, r2)),
+                _ => None,
+            },
+            0xF => match (opcode, mask_00FF(opcode)) {
+                (0xF000, _) => Some(Instruction::LoadLongImmediate),
+                (0xF002, _) => Some(Instruction::LoadAudio),
+                (_, 0x01) => Some(Instruction::SelectPlanes(r1)),
+                (_, 0x3A) => Some(Instruction::LoadPitch(r1)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // CHIP-8X opcodes. Bxyn replaces the jump with offset.
+    fn try_from_chip8x(opcode: u16) -> Option<Instruction> {
+        let r1 = mask_0F00(opcode);
+        let r2 = mask_00F0(opcode);
+        match mask_F000(opcode) {
+            0x0 if opcode == 0x02A0 => Some(Instruction::CycleBackground),
+            0x5 if mask_000F(opcode) == 0x1 => Some(Instruction::AddNibbles(r1, r2)),
+            0xB => match mask_000F(opcode) {
+                0x0 => Some(Instruction::SetColorZones(r1, r2)),
+                rows => Some(Instruction::SetColorRows(r1, r2, rows)),
+            },
+            0xE => match mask_00FF(opcode) {
+                0xF2 => Some(Instruction::SkipSecondKeyPress(r1)),
+                0xF5 => Some(Instruction::SkipNotSecondKeyPress(r1)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // CHIP-8E opcodes. BBnn and BFnn take over from the jump with offset.
+    fn try_from_chip8e(opcode: u16) -> Option<Instruction> {
+        let r1 = mask_0F00(opcode);
+        let r2 = mask_00F0(opcode);
+        match mask_F000(opcode) {
+            0x0 => match opcode {
+                0x00ED => Some(Instruction::Exit),
+                0x0151 => Some(Instruction::WaitDelay),
+                0x0188 => Some(Instruction::Skip),
+                _ => None,
+            },
+            0x5 => match mask_000F(opcode) {
+                0x1 => Some(Instruction::SkipGreater(r1, r2)),
+                0x2 => Some(Instruction::SaveRange(r1, r2)),
+                0x3 => Some(Instruction::LoadRange(r1, r2)),
+                _ => None,
+            },
+            0xB => match r1 {
+                0xB => Some(Instruction::JumpBack(mask_00FF(opcode))),
+                0xF => Some(Instruction::JumpForward(mask_00FF(opcode))),
+                _ => None,
+            },
+            0xF => match mask_00FF(opcode) {
+                0x03 => Some(Instruction::OutputPort(r1)),
+                0x1B => Some(Instruction::SkipBytes(r1)),
+                0x4F => Some(Instruction::LoadDelayAndWait(r1)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // MegaChip opcodes, all of them in the 0x0 group that is SYS otherwise.
+    fn try_from_mega_chip(opcode: u16) -> Option<Instruction> {
+        if mask_F000(opcode) != 0x0 {
+            return None;
+        }
+        let byte = mask_00FF(opcode);
+        let nibble = mask_000F(opcode);
+        match (mask_0F00(opcode), byte) {
+            (0x0, 0x10) => Some(Instruction::MegaOff),
+            (0x0, 0x11) => Some(Instruction::MegaOn),
+            (0x0, 0xB0..=0xBF) => Some(Instruction::ScrollUp(nibble)),
+            (0x1, _) => Some(Instruction::LoadMegaImmediate(byte)),
+            (0x2, _) => Some(Instruction::LoadPalette(byte)),
+            (0x3, _) => Some(Instruction::SpriteWidth(byte)),
+            (0x4, _) => Some(Instruction::SpriteHeight(byte)),
+            (0x5, _) => Some(Instruction::ScreenAlpha(byte)),
+            (0x6, 0x00..=0x0F) => Some(Instruction::PlaySample(nibble)),
+            (0x7, 0x00) => Some(Instruction::StopSample),
+            (0x8, 0x00..=0x0F) => Some(Instruction::BlendMode(nibble)),
+            (0x9, _) => Some(Instruction::CollisionColor(byte)),
+            _ => None,
+        }
+    }
+}
 
 impl InstructionParser for OpcodeMaskParser {
-    fn try_from(&self, opcode: u16) -> Result<Instruction, String> {
+    fn instruction_set(&self) -> InstructionSet {
+        self.instruction_set
+    }
+
+    fn try_from(&self, opcode: u16) -> Result<Instruction, Error> {
+        if self.instruction_set == InstructionSet::XoChip {
+            if let Some(instruction) = Self::try_from_xo_chip(opcode) {
+                return Ok(instruction);
+            }
+        }
+        if self.instruction_set == InstructionSet::Chip8X {
+            if let Some(instruction) = Self::try_from_chip8x(opcode) {
+                return Ok(instruction);
+            }
+        }
+        if self.instruction_set == InstructionSet::Chip8E {
+            if let Some(instruction) = Self::try_from_chip8e(opcode) {
+                return Ok(instruction);
+            }
+        }
+        if self.instruction_set == InstructionSet::MegaChip {
+            if let Some(instruction) = Self::try_from_mega_chip(opcode) {
+                return Ok(instruction);
+            }
+        }
+        if self.instruction_set.includes_super_chip() {
+            if let Some(instruction) = Self::try_from_super_chip(opcode) {
+                return Ok(instruction);
+            }
+        }
         match mask_F000(opcode) {
+            // The VIP hi-res interpreter clears its 64x64 screen with 0230,
+            // other interpreters leave it to the machine code call below.
+            0x0 if opcode == 0x0230 && self.instruction_set == InstructionSet::Chip8 => {
+                Ok(Instruction::ClearScreen)
+            }
             0x0 => match mask_00FF(opcode) {
                 0xE0 => Ok(Instruction::ClearScreen),
                 0xEE => Ok(Instruction::Return),
@@ -38,10 +208,10 @@ impl InstructionParser for OpcodeMaskParser {
                     0x3 => Ok(Instruction::Xor(r1, r2)),
                     0x4 => Ok(Instruction::AddRegister(r1, r2)),
                     0x5 => Ok(Instruction::SubRegister(r1, r2)),
-                    0x6 => Ok(Instruction::ShiftRight(r1)),
+                    0x6 => Ok(Instruction::ShiftRight(r1, r2)),
                     0x7 => Ok(Instruction::SubNRegister(r1, r2)),
-                    0xE => Ok(Instruction::ShiftLeft(r1)),
-                    _ => Err(format!("Invalid opcode: {:X}", opcode)),
+                    0xE => Ok(Instruction::ShiftLeft(r1, r2)),
+                    _ => Err(Error::invalid_opcode(opcode)),
                 }
             }
             0x9 => Ok(Instruction::SkipNotEqualRegister(
@@ -61,7 +231,7 @@ impl InstructionParser for OpcodeMaskParser {
                 match mask_00FF(opcode) {
                     0x9E => Ok(Instruction::SkipKeyPress(register)),
                     0xA1 => Ok(Instruction::SkipNotKeyPress(register)),
-                    _ => Err(format!("Invalid opcode: {:X}", opcode)),
+                    _ => Err(Error::invalid_opcode(opcode)),
                 }
             }
             0xF => {
@@ -76,10 +246,155 @@ impl InstructionParser for OpcodeMaskParser {
                     0x33 => Ok(Instruction::LoadIBCD(register)),
                     0x55 => Ok(Instruction::StoreRegisters(register)),
                     0x65 => Ok(Instruction::LoadRegisters(register)),
-                    _ => Err(format!("Invalid opcode: {:X}", opcode)),
+                    _ => Err(Error::invalid_opcode(opcode)),
                 }
             }
-            _ => Err(format!("opcode {:X} is invalid", opcode)),
+            _ => Err(Error::invalid_opcode(opcode)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_super_chip_opcodes() {
+        let parser = OpcodeMaskParser::new(InstructionSet::SuperChip);
+        let expected = [
+            (0x00C5, Instruction::ScrollDown(5)),
+            (0x00FB, Instruction::ScrollRight),
+            (0x00FC, Instruction::ScrollLeft),
+            (0x00FD, Instruction::Exit),
+            (0x00FE, Instruction::LowResolution),
+            (0x00FF, Instruction::HighResolution),
+            (0xD120, Instruction::DisplayLargeSprite(1, 2)),
+            (0xD125, Instruction::DisplaySprite(1, 2, 5)),
+            (0xF330, Instruction::LoadLargeFontSprite(3)),
+            (0xF475, Instruction::StoreFlags(4)),
+            (0xF585, Instruction::LoadFlags(5)),
+            (0x01FF, Instruction::SYS),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+
+        // plain CHIP-8 does not know about them
+        let parser = OpcodeMaskParser::default();
+        assert_eq!(parser.try_from(0x00FF).unwrap(), Instruction::SYS);
+        assert_eq!(
+            parser.try_from(0xD120).unwrap(),
+            Instruction::DisplaySprite(1, 2, 0)
+        );
+        assert!(parser.try_from(0xF330).is_err());
+    }
+
+    #[test]
+    fn test_xo_chip_opcodes() {
+        let parser = OpcodeMaskParser::new(InstructionSet::XoChip);
+        let expected = [
+            (0x00D3, Instruction::ScrollUp(3)),
+            (0x5122, Instruction::SaveRange(1, 2)),
+            (0x5213, Instruction::LoadRange(2, 1)),
+            (0x5120, Instruction::SkipEqualsRegister(1, 2)),
+            (0xF000, Instruction::LoadLongImmediate),
+            (0xF201, Instruction::SelectPlanes(2)),
+            (0xF002, Instruction::LoadAudio),
+            (0xF43A, Instruction::LoadPitch(4)),
+            // SUPER-CHIP instructions are part of XO-CHIP
+            (0x00FF, Instruction::HighResolution),
+            (0xF330, Instruction::LoadLargeFontSprite(3)),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+        assert_eq!(parser.instruction_set(), InstructionSet::XoChip);
+
+        let parser = OpcodeMaskParser::new(InstructionSet::SuperChip);
+        assert_eq!(parser.try_from(0x00D3).unwrap(), Instruction::SYS);
+        assert!(parser.try_from(0xF000).is_err());
+    }
+
+    #[test]
+    fn test_chip8x_opcodes() {
+        let parser = OpcodeMaskParser::new(InstructionSet::Chip8X);
+        let expected = [
+            (0x02A0, Instruction::CycleBackground),
+            (0x5121, Instruction::AddNibbles(1, 2)),
+            (0xB120, Instruction::SetColorZones(1, 2)),
+            (0xB124, Instruction::SetColorRows(1, 2, 4)),
+            (0xE3F2, Instruction::SkipSecondKeyPress(3)),
+            (0xE3F5, Instruction::SkipNotSecondKeyPress(3)),
+            (0x02A1, Instruction::SYS),
+            (0xE39E, Instruction::SkipKeyPress(3)),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+
+        let parser = OpcodeMaskParser::default();
+        assert_eq!(
+            parser.try_from(0xB120).unwrap(),
+            Instruction::JumpBase(0x120)
+        );
+    }
+
+    #[test]
+    fn test_chip8e_opcodes() {
+        let parser = OpcodeMaskParser::new(InstructionSet::Chip8E);
+        let expected = [
+            (0x00ED, Instruction::Exit),
+            (0x0151, Instruction::WaitDelay),
+            (0x0188, Instruction::Skip),
+            (0x5121, Instruction::SkipGreater(1, 2)),
+            (0x5122, Instruction::SaveRange(1, 2)),
+            (0x5123, Instruction::LoadRange(1, 2)),
+            (0xBB04, Instruction::JumpBack(4)),
+            (0xBF06, Instruction::JumpForward(6)),
+            (0xB123, Instruction::JumpBase(0x123)),
+            (0xF203, Instruction::OutputPort(2)),
+            (0xF21B, Instruction::SkipBytes(2)),
+            (0xF24F, Instruction::LoadDelayAndWait(2)),
+            (0x00FD, Instruction::SYS),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+    }
+
+    #[test]
+    fn test_vip_hires_clear_screen() {
+        let parser = OpcodeMaskParser::default();
+        assert_eq!(parser.try_from(0x0230).unwrap(), Instruction::ClearScreen);
+        assert_eq!(parser.try_from(0x0231).unwrap(), Instruction::SYS);
+        for instruction_set in [InstructionSet::SuperChip, InstructionSet::XoChip].iter() {
+            let parser = OpcodeMaskParser::new(*instruction_set);
+            assert_eq!(parser.try_from(0x0230).unwrap(), Instruction::SYS);
+        }
+    }
+
+    #[test]
+    fn test_mega_chip_opcodes() {
+        let parser = OpcodeMaskParser::new(InstructionSet::MegaChip);
+        let expected = [
+            (0x0010, Instruction::MegaOff),
+            (0x0011, Instruction::MegaOn),
+            (0x00B4, Instruction::ScrollUp(4)),
+            (0x0112, Instruction::LoadMegaImmediate(0x12)),
+            (0x0210, Instruction::LoadPalette(0x10)),
+            (0x0308, Instruction::SpriteWidth(8)),
+            (0x0400, Instruction::SpriteHeight(0)),
+            (0x05FF, Instruction::ScreenAlpha(0xFF)),
+            (0x0601, Instruction::PlaySample(1)),
+            (0x0700, Instruction::StopSample),
+            (0x0803, Instruction::BlendMode(3)),
+            (0x0901, Instruction::CollisionColor(1)),
+            (0x00FF, Instruction::HighResolution),
+            (0x0230, Instruction::LoadPalette(0x30)),
+            (0x0A00, Instruction::SYS),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
         }
     }
 }
@@ -1,4 +1,5 @@
-use crate::instructions::{Instruction, InstructionParser};
+use crate::error::Error;
+use crate::instructions::{Instruction, InstructionParser, InstructionSet};
 use crate::ophandlers;
 
 pub struct OpcodeTableEntry {
@@ -185,36 +186,323 @@ const OPCODE_TABLE: [OpcodeTableEntry; 35] = [
     }, // 0xFX65 */
 ];
 
-#[warn(dead_code)]
-pub struct OpcodeTable {}
+// The VIP hi-res interpreter clears its 64x64 screen with 0230, everywhere
+// else it is an ordinary machine code call.
+const VIP_HIRES_TABLE: [OpcodeTableEntry; 1] = [OpcodeTableEntry {
+    opcode: 0x0230,
+    mask: 0xFFFF,
+    handler: ophandlers::handle0x0230,
+}]; // 0x0230
+
+// Checked before OPCODE_TABLE, where most of these would match 0x0NNN.
+const SUPER_CHIP_TABLE: [OpcodeTableEntry; 10] = [
+    OpcodeTableEntry {
+        opcode: 0x00C0,
+        mask: 0xFFF0,
+        handler: ophandlers::handle0x00CN,
+    }, // 0x00CN
+    OpcodeTableEntry {
+        opcode: 0x00FB,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x00FB,
+    }, // 0x00FB
+    OpcodeTableEntry {
+        opcode: 0x00FC,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x00FC,
+    }, // 0x00FC
+    OpcodeTableEntry {
+        opcode: 0x00FD,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x00FD,
+    }, // 0x00FD
+    OpcodeTableEntry {
+        opcode: 0x00FE,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x00FE,
+    }, // 0x00FE
+    OpcodeTableEntry {
+        opcode: 0x00FF,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x00FF,
+    }, // 0x00FF
+    OpcodeTableEntry {
+        opcode: 0xD000,
+        mask: 0xF00F,
+        handler: ophandlers::handle0xDXY0,
+    }, // 0xDXY0
+    OpcodeTableEntry {
+        opcode: 0xF030,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFX30,
+    }, // 0xFX30
+    OpcodeTableEntry {
+        opcode: 0xF075,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFX75,
+    }, // 0xFX75
+    OpcodeTableEntry {
+        opcode: 0xF085,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFX85,
+    }, // 0xFX85
+];
+
+// Checked before SUPER_CHIP_TABLE, XO-CHIP includes all of SUPER-CHIP.
+const XO_CHIP_TABLE: [OpcodeTableEntry; 7] = [
+    OpcodeTableEntry {
+        opcode: 0x00D0,
+        mask: 0xFFF0,
+        handler: ophandlers::handle0x00DN,
+    }, // 0x00DN
+    OpcodeTableEntry {
+        opcode: 0x5002,
+        mask: 0xF00F,
+        handler: ophandlers::handle0x5XY2,
+    }, // 0x5XY2
+    OpcodeTableEntry {
+        opcode: 0x5003,
+        mask: 0xF00F,
+        handler: ophandlers::handle0x5XY3,
+    }, // 0x5XY3
+    OpcodeTableEntry {
+        opcode: 0xF000,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0xF000,
+    }, // 0xF000
+    OpcodeTableEntry {
+        opcode: 0xF002,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0xF002,
+    }, // 0xF002
+    OpcodeTableEntry {
+        opcode: 0xF001,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFN01,
+    }, // 0xFN01
+    OpcodeTableEntry {
+        opcode: 0xF03A,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFX3A,
+    }, // 0xFX3A
+];
+
+// Bxy0 has to come before Bxyn, which matches any Bxxx.
+const CHIP_8X_TABLE: [OpcodeTableEntry; 6] = [
+    OpcodeTableEntry {
+        opcode: 0x02A0,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x02A0,
+    }, // 0x02A0
+    OpcodeTableEntry {
+        opcode: 0x5001,
+        mask: 0xF00F,
+        handler: ophandlers::handle0x5XY1,
+    }, // 0x5XY1
+    OpcodeTableEntry {
+        opcode: 0xB000,
+        mask: 0xF00F,
+        handler: ophandlers::handle0xBXY0,
+    }, // 0xBXY0
+    OpcodeTableEntry {
+        opcode: 0xB000,
+        mask: 0xF000,
+        handler: ophandlers::handle0xBXYN,
+    }, // 0xBXYN
+    OpcodeTableEntry {
+        opcode: 0xE0F2,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xEXF2,
+    }, // 0xEXF2
+    OpcodeTableEntry {
+        opcode: 0xE0F5,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xEXF5,
+    }, // 0xEXF5
+];
+
+const CHIP_8E_TABLE: [OpcodeTableEntry; 11] = [
+    OpcodeTableEntry {
+        opcode: 0x00ED,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x00ED,
+    }, // 0x00ED
+    OpcodeTableEntry {
+        opcode: 0x0151,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x0151,
+    }, // 0x0151
+    OpcodeTableEntry {
+        opcode: 0x0188,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x0188,
+    }, // 0x0188
+    OpcodeTableEntry {
+        opcode: 0x5001,
+        mask: 0xF00F,
+        handler: ophandlers::handle0x5XY1_chip8e,
+    }, // 0x5XY1
+    OpcodeTableEntry {
+        opcode: 0x5002,
+        mask: 0xF00F,
+        handler: ophandlers::handle0x5XY2,
+    }, // 0x5XY2
+    OpcodeTableEntry {
+        opcode: 0x5003,
+        mask: 0xF00F,
+        handler: ophandlers::handle0x5XY3,
+    }, // 0x5XY3
+    OpcodeTableEntry {
+        opcode: 0xBB00,
+        mask: 0xFF00,
+        handler: ophandlers::handle0xBBNN,
+    }, // 0xBBNN
+    OpcodeTableEntry {
+        opcode: 0xBF00,
+        mask: 0xFF00,
+        handler: ophandlers::handle0xBFNN,
+    }, // 0xBFNN
+    OpcodeTableEntry {
+        opcode: 0xF003,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFX03,
+    }, // 0xFX03
+    OpcodeTableEntry {
+        opcode: 0xF01B,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFX1B,
+    }, // 0xFX1B
+    OpcodeTableEntry {
+        opcode: 0xF04F,
+        mask: 0xF0FF,
+        handler: ophandlers::handle0xFX4F,
+    }, // 0xFX4F
+];
+
+// Checked before SUPER_CHIP_TABLE, MegaChip includes all of SUPER-CHIP.
+const MEGA_CHIP_TABLE: [OpcodeTableEntry; 12] = [
+    OpcodeTableEntry {
+        opcode: 0x0010,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x0010,
+    }, // 0x0010
+    OpcodeTableEntry {
+        opcode: 0x0011,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x0011,
+    }, // 0x0011
+    OpcodeTableEntry {
+        opcode: 0x00B0,
+        mask: 0xFFF0,
+        handler: ophandlers::handle0x00BN,
+    }, // 0x00BN
+    OpcodeTableEntry {
+        opcode: 0x0100,
+        mask: 0xFF00,
+        handler: ophandlers::handle0x01NN,
+    }, // 0x01NN
+    OpcodeTableEntry {
+        opcode: 0x0200,
+        mask: 0xFF00,
+        handler: ophandlers::handle0x02NN,
+    }, // 0x02NN
+    OpcodeTableEntry {
+        opcode: 0x0300,
+        mask: 0xFF00,
+        handler: ophandlers::handle0x03NN,
+    }, // 0x03NN
+    OpcodeTableEntry {
+        opcode: 0x0400,
+        mask: 0xFF00,
+        handler: ophandlers::handle0x04NN,
+    }, // 0x04NN
+    OpcodeTableEntry {
+        opcode: 0x0500,
+        mask: 0xFF00,
+        handler: ophandlers::handle0x05NN,
+    }, // 0x05NN
+    OpcodeTableEntry {
+        opcode: 0x0600,
+        mask: 0xFFF0,
+        handler: ophandlers::handle0x060N,
+    }, // 0x060N
+    OpcodeTableEntry {
+        opcode: 0x0700,
+        mask: 0xFFFF,
+        handler: ophandlers::handle0x0700,
+    }, // 0x0700
+    OpcodeTableEntry {
+        opcode: 0x0800,
+        mask: 0xFFF0,
+        handler: ophandlers::handle0x080N,
+    }, // 0x080N
+    OpcodeTableEntry {
+        opcode: 0x0900,
+        mask: 0xFF00,
+        handler: ophandlers::handle0x09NN,
+    }, // 0x09NN
+];
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OpcodeTable {
+    instruction_set: InstructionSet,
+}
+
+impl OpcodeTable {
+    pub fn new(instruction_set: InstructionSet) -> Self {
+        Self { instruction_set }
+    }
+
+    // Tables for the extensions of the instruction set, most specific first.
+    fn extension_tables(&self) -> &'static [&'static [OpcodeTableEntry]] {
+        match self.instruction_set {
+            InstructionSet::Chip8 => &[&VIP_HIRES_TABLE],
+            InstructionSet::SuperChip => &[&SUPER_CHIP_TABLE],
+            InstructionSet::XoChip => &[&XO_CHIP_TABLE, &SUPER_CHIP_TABLE],
+            InstructionSet::Chip8X => &[&CHIP_8X_TABLE],
+            InstructionSet::Chip8E => &[&CHIP_8E_TABLE],
+            InstructionSet::MegaChip => &[&MEGA_CHIP_TABLE, &SUPER_CHIP_TABLE],
+        }
+    }
+}
 
 impl InstructionParser for OpcodeTable {
-    fn try_from(&self, opcode: u16) -> Result<Instruction, String> {
+    fn instruction_set(&self) -> InstructionSet {
+        self.instruction_set
+    }
+
+    fn try_from(&self, opcode: u16) -> Result<Instruction, Error> {
         let ins: Instruction;
-        for opcode_entry in OPCODE_TABLE.iter() {
+        let extensions = self
+            .extension_tables()
+            .iter()
+            .flat_map(|table| table.iter());
+        for opcode_entry in extensions.chain(OPCODE_TABLE.iter()) {
             if opcode != 0 && (opcode & opcode_entry.mask == opcode_entry.opcode) {
                 // debug!("input opcode = {:X}, mask = {:X}, actual code: {:X}", opcode, opcode_entry.mask, opcode_entry.opcode);
                 ins = (opcode_entry.handler)(opcode);
                 return Ok(ins);
             }
         }
-        Err(format!("Opcode not found: {:X}", opcode))
+        Err(Error::invalid_opcode(opcode))
     }
 }
 
 #[cfg(test)]
-use crate::bitmasks::*;
-use std::collections::HashMap;
 mod tests {
     use super::*;
+    use crate::bitmasks::*;
+    use crate::error::ErrorKind;
+    use std::collections::HashMap;
 
     #[test]
     fn test_opcode_table_simple() {
         let mut opcode_hash: HashMap<u16, Instruction> = HashMap::new();
-        let parser = OpcodeTable {};
+        let parser = OpcodeTable::default();
 
         opcode_hash.insert(0x00E0, Instruction::ClearScreen);
         opcode_hash.insert(0x00EE, Instruction::Return);
+        opcode_hash.insert(0x0230, Instruction::ClearScreen);
         opcode_hash.insert(0x06B5, Instruction::SYS);
         opcode_hash.insert(0x16B5, Instruction::Jump(mask_0FFF(0x16B5)));
         opcode_hash.insert(0x26B5, Instruction::Call(mask_0FFF(0x26B5)));
@@ -262,12 +550,18 @@ mod tests {
             0x8DB5,
             Instruction::SubRegister(mask_0F00(0x8DB5), mask_00F0(0x8DB5)),
         );
-        opcode_hash.insert(0x8DB6, Instruction::ShiftRight(mask_0F00(0x8DB6)));
+        opcode_hash.insert(
+            0x8DB6,
+            Instruction::ShiftRight(mask_0F00(0x8DB6), mask_00F0(0x8DB6)),
+        );
         opcode_hash.insert(
             0x8DB7,
             Instruction::SubNRegister(mask_0F00(0x8DB7), mask_00F0(0x8DB7)),
         );
-        opcode_hash.insert(0x8DBE, Instruction::ShiftLeft(mask_0F00(0x8DBE)));
+        opcode_hash.insert(
+            0x8DBE,
+            Instruction::ShiftLeft(mask_0F00(0x8DBE), mask_00F0(0x8DBE)),
+        );
         opcode_hash.insert(
             0x9DB0,
             Instruction::SkipNotEqualRegister(mask_0F00(0x9DB0), mask_00F0(0x9DB0)),
@@ -300,14 +594,137 @@ mod tests {
 
     #[test]
     fn test_bad_opcodes() {
-        let parser = OpcodeTable {};
+        let parser = OpcodeTable::default();
         // Some negative tests for opcode construction
         let opcode = 0xFC14;
-        let instruction = parser.try_from(opcode);
-        assert_eq!(instruction, Err(format!("Opcode not found: {:X}", opcode)));
+        let error = parser.try_from(opcode).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::InvalidOpcode));
+        assert_eq!(error.opcode(), Some(opcode));
 
         let opcode = 0xEB8E;
-        let instruction = parser.try_from(opcode);
-        assert_eq!(instruction, Err(format!("Opcode not found: {:X}", opcode)));
+        let error = parser.try_from(opcode).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::InvalidOpcode));
+        assert_eq!(error.opcode(), Some(opcode));
+    }
+
+    #[test]
+    fn test_opcode_table_super_chip() {
+        let parser = OpcodeTable::new(InstructionSet::SuperChip);
+        let expected = [
+            (0x00C5, Instruction::ScrollDown(5)),
+            (0x00FB, Instruction::ScrollRight),
+            (0x00FC, Instruction::ScrollLeft),
+            (0x00FD, Instruction::Exit),
+            (0x00FE, Instruction::LowResolution),
+            (0x00FF, Instruction::HighResolution),
+            (0xD120, Instruction::DisplayLargeSprite(1, 2)),
+            (0xD125, Instruction::DisplaySprite(1, 2, 5)),
+            (0xF330, Instruction::LoadLargeFontSprite(3)),
+            (0xF475, Instruction::StoreFlags(4)),
+            (0xF585, Instruction::LoadFlags(5)),
+            (0x00E0, Instruction::ClearScreen),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+
+        let parser = OpcodeTable::default();
+        assert_eq!(parser.try_from(0x00FF).unwrap(), Instruction::SYS);
+        assert!(parser.try_from(0xF330).is_err());
+    }
+
+    #[test]
+    fn test_opcode_table_vip_hires_clear_screen() {
+        let parser = OpcodeTable::default();
+        assert_eq!(parser.try_from(0x0230).unwrap(), Instruction::ClearScreen);
+        // only the VIP hi-res interpreter treats it as a clear
+        for instruction_set in [InstructionSet::SuperChip, InstructionSet::XoChip].iter() {
+            let parser = OpcodeTable::new(*instruction_set);
+            assert_eq!(parser.try_from(0x0230).unwrap(), Instruction::SYS);
+        }
+    }
+
+    #[test]
+    fn test_opcode_table_xo_chip() {
+        let parser = OpcodeTable::new(InstructionSet::XoChip);
+        let expected = [
+            (0x00D3, Instruction::ScrollUp(3)),
+            (0x5122, Instruction::SaveRange(1, 2)),
+            (0x5213, Instruction::LoadRange(2, 1)),
+            (0x5120, Instruction::SkipEqualsRegister(1, 2)),
+            (0xF000, Instruction::LoadLongImmediate),
+            (0xF201, Instruction::SelectPlanes(2)),
+            (0xF002, Instruction::LoadAudio),
+            (0xF43A, Instruction::LoadPitch(4)),
+            (0x00FF, Instruction::HighResolution),
+            (0xF330, Instruction::LoadLargeFontSprite(3)),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+        assert_eq!(parser.instruction_set(), InstructionSet::XoChip);
+    }
+
+    #[test]
+    fn test_opcode_table_chip8x() {
+        let parser = OpcodeTable::new(InstructionSet::Chip8X);
+        let expected = [
+            (0x02A0, Instruction::CycleBackground),
+            (0x5121, Instruction::AddNibbles(1, 2)),
+            (0xB120, Instruction::SetColorZones(1, 2)),
+            (0xB124, Instruction::SetColorRows(1, 2, 4)),
+            (0xE3F2, Instruction::SkipSecondKeyPress(3)),
+            (0xE3F5, Instruction::SkipNotSecondKeyPress(3)),
+            (0x02A1, Instruction::SYS),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+    }
+
+    #[test]
+    fn test_opcode_table_chip8e() {
+        let parser = OpcodeTable::new(InstructionSet::Chip8E);
+        let expected = [
+            (0x00ED, Instruction::Exit),
+            (0x0151, Instruction::WaitDelay),
+            (0x0188, Instruction::Skip),
+            (0x5121, Instruction::SkipGreater(1, 2)),
+            (0x5122, Instruction::SaveRange(1, 2)),
+            (0x5123, Instruction::LoadRange(1, 2)),
+            (0xBB04, Instruction::JumpBack(4)),
+            (0xBF06, Instruction::JumpForward(6)),
+            (0xB123, Instruction::JumpBase(0x123)),
+            (0xF203, Instruction::OutputPort(2)),
+            (0xF21B, Instruction::SkipBytes(2)),
+            (0xF24F, Instruction::LoadDelayAndWait(2)),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
+    }
+
+    #[test]
+    fn test_opcode_table_mega_chip() {
+        let parser = OpcodeTable::new(InstructionSet::MegaChip);
+        let expected = [
+            (0x0010, Instruction::MegaOff),
+            (0x0011, Instruction::MegaOn),
+            (0x00B4, Instruction::ScrollUp(4)),
+            (0x0112, Instruction::LoadMegaImmediate(0x12)),
+            (0x0210, Instruction::LoadPalette(0x10)),
+            (0x0308, Instruction::SpriteWidth(8)),
+            (0x0400, Instruction::SpriteHeight(0)),
+            (0x05FF, Instruction::ScreenAlpha(0xFF)),
+            (0x0601, Instruction::PlaySample(1)),
+            (0x0700, Instruction::StopSample),
+            (0x0803, Instruction::BlendMode(3)),
+            (0x0901, Instruction::CollisionColor(1)),
+            (0x00FF, Instruction::HighResolution),
+            (0x0A00, Instruction::SYS),
+        ];
+        for (opcode, instruction) in expected.iter() {
+            assert_eq!(parser.try_from(*opcode).unwrap(), *instruction);
+        }
     }
 }
@@ -6,6 +6,12 @@ pub const fn handle0x00E0(_opcode: u16) -> Instruction {
     Instruction::ClearScreen
 }
 
+// CLS of the VIP hi-res interpreter.
+#[allow(non_snake_case)]
+pub const fn handle0x0230(_opcode: u16) -> Instruction {
+    Instruction::ClearScreen
+}
+
 #[allow(non_snake_case)]
 pub const fn handle0x00EE(_opcode: u16) -> Instruction {
     Instruction::Return
@@ -96,7 +102,8 @@ pub const fn handle0x8XY5(opcode: u16) -> Instruction {
 #[allow(non_snake_case)]
 pub const fn handle0x8XY6(opcode: u16) -> Instruction {
     let r1 = mask_0F00(opcode);
-    Instruction::ShiftRight(r1)
+    let r2 = mask_00F0(opcode);
+    Instruction::ShiftRight(r1, r2)
 }
 
 #[allow(non_snake_case)]
@@ -109,7 +116,8 @@ pub const fn handle0x8XY7(opcode: u16) -> Instruction {
 #[allow(non_snake_case)]
 pub const fn handle0x8XYE(opcode: u16) -> Instruction {
     let r1 = mask_0F00(opcode);
-    Instruction::ShiftLeft(r1)
+    let r2 = mask_00F0(opcode);
+    Instruction::ShiftLeft(r1, r2)
 }
 
 #[allow(non_snake_case)]
@@ -202,3 +210,232 @@ pub const fn handle0xFX65(opcode: u16) -> Instruction {
     let register = mask_0F00(opcode);
     Instruction::LoadRegisters(register)
 }
+
+#[allow(non_snake_case)]
+pub const fn handle0x00CN(opcode: u16) -> Instruction {
+    Instruction::ScrollDown(mask_000F(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00FB(_opcode: u16) -> Instruction {
+    Instruction::ScrollRight
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00FC(_opcode: u16) -> Instruction {
+    Instruction::ScrollLeft
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00FD(_opcode: u16) -> Instruction {
+    Instruction::Exit
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00FE(_opcode: u16) -> Instruction {
+    Instruction::LowResolution
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00FF(_opcode: u16) -> Instruction {
+    Instruction::HighResolution
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xDXY0(opcode: u16) -> Instruction {
+    Instruction::DisplayLargeSprite(mask_0F00(opcode), mask_00F0(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFX30(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::LoadLargeFontSprite(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFX75(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::StoreFlags(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFX85(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::LoadFlags(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00DN(opcode: u16) -> Instruction {
+    Instruction::ScrollUp(mask_000F(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x5XY2(opcode: u16) -> Instruction {
+    Instruction::SaveRange(mask_0F00(opcode), mask_00F0(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x5XY3(opcode: u16) -> Instruction {
+    Instruction::LoadRange(mask_0F00(opcode), mask_00F0(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xF000(_opcode: u16) -> Instruction {
+    Instruction::LoadLongImmediate
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFN01(opcode: u16) -> Instruction {
+    Instruction::SelectPlanes(mask_0F00(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xF002(_opcode: u16) -> Instruction {
+    Instruction::LoadAudio
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFX3A(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::LoadPitch(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x02A0(_opcode: u16) -> Instruction {
+    Instruction::CycleBackground
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x5XY1(opcode: u16) -> Instruction {
+    Instruction::AddNibbles(mask_0F00(opcode), mask_00F0(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xBXY0(opcode: u16) -> Instruction {
+    Instruction::SetColorZones(mask_0F00(opcode), mask_00F0(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xBXYN(opcode: u16) -> Instruction {
+    Instruction::SetColorRows(mask_0F00(opcode), mask_00F0(opcode), mask_000F(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xEXF2(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::SkipSecondKeyPress(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xEXF5(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::SkipNotSecondKeyPress(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00ED(_opcode: u16) -> Instruction {
+    Instruction::Exit
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x0151(_opcode: u16) -> Instruction {
+    Instruction::WaitDelay
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x0188(_opcode: u16) -> Instruction {
+    Instruction::Skip
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x5XY1_chip8e(opcode: u16) -> Instruction {
+    Instruction::SkipGreater(mask_0F00(opcode), mask_00F0(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xBBNN(opcode: u16) -> Instruction {
+    Instruction::JumpBack(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xBFNN(opcode: u16) -> Instruction {
+    Instruction::JumpForward(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFX03(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::OutputPort(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFX1B(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::SkipBytes(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0xFX4F(opcode: u16) -> Instruction {
+    let register = mask_0F00(opcode);
+    Instruction::LoadDelayAndWait(register)
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x0010(_opcode: u16) -> Instruction {
+    Instruction::MegaOff
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x0011(_opcode: u16) -> Instruction {
+    Instruction::MegaOn
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x01NN(opcode: u16) -> Instruction {
+    Instruction::LoadMegaImmediate(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x02NN(opcode: u16) -> Instruction {
+    Instruction::LoadPalette(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x03NN(opcode: u16) -> Instruction {
+    Instruction::SpriteWidth(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x04NN(opcode: u16) -> Instruction {
+    Instruction::SpriteHeight(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x05NN(opcode: u16) -> Instruction {
+    Instruction::ScreenAlpha(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x060N(opcode: u16) -> Instruction {
+    Instruction::PlaySample(mask_000F(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x0700(_opcode: u16) -> Instruction {
+    Instruction::StopSample
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x080N(opcode: u16) -> Instruction {
+    Instruction::BlendMode(mask_000F(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x09NN(opcode: u16) -> Instruction {
+    Instruction::CollisionColor(mask_00FF(opcode))
+}
+
+#[allow(non_snake_case)]
+pub const fn handle0x00BN(opcode: u16) -> Instruction {
+    Instruction::ScrollUp(mask_000F(opcode))
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core::MachineConfig;
+use crate::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH, ETI_660_HEIGHT, VIP_HIRES_HEIGHT};
+use crate::error::{Error, ErrorKind};
+use crate::instructions::InstructionSet;
+use crate::quirks::Quirks;
+
+/**
+ * A CHIP-8 variant together with the machine it ran on.
+ * Picking a platform decides the instruction set, memory size, stack depth,
+ * where programs are loaded, the display geometry, quirks and clock speed,
+ * so switching targets is a single choice.
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    #[default]
+    Chip8,
+    SuperChip,
+    XoChip,
+    Chip8X,
+    Chip8E,
+    VipHires, // the 64x64 VIP hi-res interpreter
+    MegaChip,
+    Eti660,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 8] = [
+        Platform::Chip8,
+        Platform::SuperChip,
+        Platform::XoChip,
+        Platform::Chip8X,
+        Platform::Chip8E,
+        Platform::VipHires,
+        Platform::MegaChip,
+        Platform::Eti660,
+    ];
+
+    // Name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+            Platform::Chip8X => "chip8x",
+            Platform::Chip8E => "chip8e",
+            Platform::VipHires => "vip-hires",
+            Platform::MegaChip => "megachip",
+            Platform::Eti660 => "eti660",
+        }
+    }
+
+    pub fn instruction_set(self) -> InstructionSet {
+        match self {
+            Platform::Chip8 | Platform::VipHires | Platform::Eti660 => InstructionSet::Chip8,
+            Platform::SuperChip => InstructionSet::SuperChip,
+            Platform::XoChip => InstructionSet::XoChip,
+            Platform::Chip8X => InstructionSet::Chip8X,
+            Platform::Chip8E => InstructionSet::Chip8E,
+            Platform::MegaChip => InstructionSet::MegaChip,
+        }
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => 0x10000,
+            Platform::MegaChip => 0x200_0000,
+            _ => 0x1000,
+        }
+    }
+
+    // The VIP interpreters only had room for 12 return addresses.
+    pub fn stack_size(self) -> usize {
+        match self {
+            Platform::VipHires | Platform::Chip8X | Platform::Chip8E => 12,
+            _ => 16,
+        }
+    }
+
+    pub fn program_offset(self) -> u16 {
+        match self {
+            Platform::Chip8X => 0x300,
+            Platform::Eti660 => 0x600,
+            _ => 0x200,
+        }
+    }
+
+    pub fn config(self) -> MachineConfig {
+        MachineConfig {
+            memory_size: self.memory_size(),
+            stack_size: self.stack_size(),
+            program_offset: self.program_offset(),
+        }
+    }
+
+    // Width and height of the display when the machine starts.
+    pub fn display_size(self) -> (usize, usize) {
+        match self {
+            Platform::VipHires => (DISPLAY_WIDTH, VIP_HIRES_HEIGHT),
+            Platform::Eti660 => (DISPLAY_WIDTH, ETI_660_HEIGHT),
+            _ => (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::SuperChip | Platform::MegaChip => Quirks::SUPER_CHIP,
+            Platform::XoChip => Quirks::XO_CHIP,
+            _ => Quirks::COSMAC_VIP,
+        }
+    }
+
+    // Instructions per 60 Hz frame.
+    pub fn cycles_per_frame(self) -> u32 {
+        match self {
+            Platform::SuperChip => 30,
+            Platform::XoChip => 200,
+            Platform::MegaChip => 1000,
+            _ => 10,
+        }
+    }
+}
+
+impl From<InstructionSet> for Platform {
+    fn from(instruction_set: InstructionSet) -> Self {
+        match instruction_set {
+            InstructionSet::Chip8 => Platform::Chip8,
+            InstructionSet::SuperChip => Platform::SuperChip,
+            InstructionSet::XoChip => Platform::XoChip,
+            InstructionSet::Chip8X => Platform::Chip8X,
+            InstructionSet::Chip8E => Platform::Chip8E,
+            InstructionSet::MegaChip => Platform::MegaChip,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Platform::ALL
+            .iter()
+            .find(|platform| platform.name().eq_ignore_ascii_case(name))
+            .copied()
+            .ok_or_else(|| ErrorKind::UnknownPlatform(name.to_string()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        for platform in Platform::ALL.iter() {
+            assert_eq!(platform.name().parse::<Platform>().unwrap(), *platform);
+        }
+        assert_eq!("SCHIP".parse::<Platform>().unwrap(), Platform::SuperChip);
+        let error = "chip9".parse::<Platform>().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::UnknownPlatform(name) if name == "chip9"));
+    }
+}
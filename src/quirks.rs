@@ -0,0 +1,68 @@
+// How far Fx55/Fx65 move I after storing or loading registers V0 to Vx.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    Unchanged,
+    ByX,
+    ByXPlusOne,
+}
+
+/**
+ * Behaviour that differs between historical interpreters.
+ * ROMs were written against whichever interpreter their author had, so the
+ * machine consults these at execution time instead of hardcoding one
+ * interpretation. The presets cover the main interpreters.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    pub shift_uses_vy: bool, // 8xy6/8xyE shift Vy into Vx instead of shifting Vx
+    pub index_increment: IndexIncrement, // Fx55/Fx65
+    pub logic_resets_vf: bool, // 8xy1/8xy2/8xy3 set VF to 0
+    pub jump_uses_vx: bool,  // Bxnn jumps to xnn + Vx instead of nnn + V0
+    pub sprites_wrap: bool,  // sprites wrap around the screen edges instead of clipping
+    pub display_wait: bool,  // Dxyn waits for the vertical blank, one sprite per frame
+}
+
+impl Quirks {
+    pub const COSMAC_VIP: Self = Self {
+        shift_uses_vy: true,
+        index_increment: IndexIncrement::ByXPlusOne,
+        logic_resets_vf: true,
+        jump_uses_vx: false,
+        sprites_wrap: false,
+        display_wait: true,
+    };
+
+    pub const CHIP_48: Self = Self {
+        shift_uses_vy: false,
+        index_increment: IndexIncrement::ByX,
+        logic_resets_vf: false,
+        jump_uses_vx: true,
+        sprites_wrap: false,
+        display_wait: false,
+    };
+
+    pub const SUPER_CHIP: Self = Self {
+        shift_uses_vy: false,
+        index_increment: IndexIncrement::Unchanged,
+        logic_resets_vf: false,
+        jump_uses_vx: true,
+        sprites_wrap: false,
+        display_wait: false,
+    };
+
+    // As implemented by Octo, the reference for XO-CHIP.
+    pub const XO_CHIP: Self = Self {
+        shift_uses_vy: true,
+        index_increment: IndexIncrement::ByXPlusOne,
+        logic_resets_vf: false,
+        jump_uses_vx: false,
+        sprites_wrap: true,
+        display_wait: false,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::COSMAC_VIP
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::core::Machine;
+use crate::error::{ErrorKind, Result};
+use crate::frame::Frame;
+use crate::instructions::InstructionParser;
+use crate::screenshot::ScreenshotOptions;
+use crate::timers::TIMER_FREQUENCY;
+
+// GIF frame delays are counted in hundredths of a second.
+const GIF_TIME_BASE: u64 = 100;
+const GIF_MAX_COLORS: usize = 256;
+// NeuQuant speed for frames with more colours than a GIF palette holds.
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Gif,
+    Y4m, // uncompressed 4:4:4 YUV at 60 frames per second, for piping to an encoder
+}
+
+impl RecordingFormat {
+    // Picked from the file extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Ok(RecordingFormat::Gif),
+            "y4m" => Ok(RecordingFormat::Y4m),
+            _ => Err(ErrorKind::UnknownVideoFormat(path.display().to_string()).into()),
+        }
+    }
+}
+
+type Output = Box<dyn Write + Send>;
+
+enum Encoder {
+    Waiting(Output), // for the first frame to say how big the recording is
+    Gif(gif::Encoder<Output>),
+    Y4m(Output),
+    Finished,
+}
+
+/**
+ * Records what the machine shows, one frame per 60 Hz tick.
+ * The size of the recording is set by the first frame; frames of another
+ * size, e.g. after a switch to hi-res, are stretched to fit.
+ * GIFs only get a new image when the screen changes, shown for as long as
+ * the ticks it stayed up add up to. Y4M streams get every frame.
+*/
+pub struct Recorder {
+    format: RecordingFormat,
+    encoder: Encoder,
+    options: ScreenshotOptions,
+    size: (usize, usize),
+    ticks: u64,                    // frames recorded so far
+    pending: Option<(Frame, u64)>, // GIF image not written yet and the tick it appeared at
+}
+
+fn gif_error(error: gif::EncodingError) -> io::Error {
+    match error {
+        gif::EncodingError::Io(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+// Tick number converted to GIF time, rounded to the nearest hundredth.
+fn gif_time(tick: u64) -> u64 {
+    let frequency = u64::from(TIMER_FREQUENCY);
+    (tick * GIF_TIME_BASE + frequency / 2) / frequency
+}
+
+// GIFs store their width and height in 16 bits.
+fn gif_size(width: usize, height: usize) -> Result<(u16, u16)> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(ErrorKind::GifTooLarge { width, height }.into()),
+    }
+}
+
+// Palette indices stay below GIF_MAX_COLORS, so they fit in a byte.
+#[allow(clippy::cast_possible_truncation)]
+fn gif_frame(frame: &Frame) -> Result<gif::Frame<'static>> {
+    let (width, height) = gif_size(frame.width, frame.height)?;
+    let mut colors: HashMap<u32, usize> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(frame.pixels.len());
+    for rgb in frame.pixels.iter() {
+        let index = match colors.get(rgb) {
+            Some(index) => *index,
+            None if colors.len() < GIF_MAX_COLORS => {
+                palette.extend_from_slice(&rgb.to_be_bytes()[1..]);
+                colors.insert(*rgb, colors.len());
+                colors.len() - 1
+            }
+            None => {
+                let mut data = Vec::with_capacity(frame.pixels.len() * 3);
+                for rgb in frame.pixels.iter() {
+                    data.extend_from_slice(&rgb.to_be_bytes()[1..]);
+                }
+                return Ok(gif::Frame::from_rgb_speed(
+                    width,
+                    height,
+                    &data,
+                    GIF_QUANTIZE_SPEED,
+                ));
+            }
+        };
+        indices.push(index as u8);
+    }
+    Ok(gif::Frame::from_palette_pixels(
+        width, height, indices, palette, None,
+    ))
+}
+
+// Full range BT.601, the same conversion JPEG uses.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn yuv(rgb: u32) -> [u8; 3] {
+    let r = f64::from((rgb >> 16) & 0xFF);
+    let g = f64::from((rgb >> 8) & 0xFF);
+    let b = f64::from(rgb & 0xFF);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let u = 128.0 - 0.168_736 * r - 0.331_264 * g + 0.5 * b;
+    let v = 128.0 + 0.5 * r - 0.418_688 * g - 0.081_312 * b;
+    [y, u, v].map(|c| c.round().clamp(0.0, 255.0) as u8)
+}
+
+impl Recorder {
+    // Record to a file, GIF or Y4M depending on its extension.
+    pub fn create<P: AsRef<Path>>(path: P, options: ScreenshotOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let format = RecordingFormat::from_path(path)?;
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Self::new(Box::new(file), format, options))
+    }
+
+    pub fn new(output: Output, format: RecordingFormat, options: ScreenshotOptions) -> Self {
+        Self {
+            format,
+            encoder: Encoder::Waiting(output),
+            options,
+            size: (0, 0),
+            ticks: 0,
+            pending: None,
+        }
+    }
+
+    pub fn frames_recorded(&self) -> u64 {
+        self.ticks
+    }
+
+    // Call once per 60 Hz frame.
+    pub fn record<T: InstructionParser>(&mut self, machine: &Machine<T>) -> Result<()> {
+        let frame = machine.frame(&self.options.palette);
+        if let Encoder::Waiting(_) = self.encoder {
+            let scale = self.options.scale.max(1);
+            self.size = (frame.width * scale, frame.height * scale);
+            self.start()?;
+        }
+        let frame = frame.resized(self.size.0, self.size.1);
+        match &mut self.encoder {
+            Encoder::Y4m(output) => {
+                output.write_all(b"FRAME\n")?;
+                let pixels: Vec<[u8; 3]> = frame.pixels.iter().map(|rgb| yuv(*rgb)).collect();
+                for plane in 0..3 {
+                    let data: Vec<u8> = pixels.iter().map(|yuv| yuv[plane]).collect();
+                    output.write_all(&data)?;
+                }
+            }
+            Encoder::Gif(_) => {
+                let changed = self.pending.as_ref().is_none_or(|(last, _)| *last != frame);
+                if changed {
+                    self.write_pending()?;
+                    self.pending = Some((frame, self.ticks));
+                }
+            }
+            Encoder::Waiting(_) | Encoder::Finished => {}
+        }
+        self.ticks += 1;
+        Ok(())
+    }
+
+    // Write out whatever is left, after this nothing more is recorded.
+    pub fn finish(&mut self) -> Result<()> {
+        self.write_pending()?;
+        match std::mem::replace(&mut self.encoder, Encoder::Finished) {
+            Encoder::Gif(encoder) => encoder.into_inner()?.flush()?,
+            Encoder::Y4m(mut output) | Encoder::Waiting(mut output) => output.flush()?,
+            Encoder::Finished => {}
+        }
+        Ok(())
+    }
+
+    // Nothing is written if the size is no good, the recorder keeps waiting.
+    fn start(&mut self) -> Result<()> {
+        let (width, height) = self.size;
+        if self.format == RecordingFormat::Gif {
+            gif_size(width, height)?;
+        }
+        let output = match std::mem::replace(&mut self.encoder, Encoder::Finished) {
+            Encoder::Waiting(output) => output,
+            _ => unreachable!("recording already started"),
+        };
+        self.encoder = match self.format {
+            RecordingFormat::Gif => {
+                let (width, height) = gif_size(width, height)?;
+                let mut encoder =
+                    gif::Encoder::new(output, width, height, &[]).map_err(gif_error)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(gif_error)?;
+                Encoder::Gif(encoder)
+            }
+            RecordingFormat::Y4m => {
+                let mut output = output;
+                writeln!(
+                    output,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=FULL",
+                    width, height, TIMER_FREQUENCY
+                )?;
+                Encoder::Y4m(output)
+            }
+        };
+        Ok(())
+    }
+
+    fn write_pending(&mut self) -> Result<()> {
+        if let (Some((frame, since)), Encoder::Gif(encoder)) =
+            (self.pending.take(), &mut self.encoder)
+        {
+            let mut image = gif_frame(&frame)?;
+            let delay = gif_time(self.ticks) - gif_time(since);
+            image.delay = u16::try_from(delay).unwrap_or(u16::MAX);
+            encoder.write_frame(&image).map_err(gif_error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            error!("Unable to finish the recording: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // A writer the test can look into after the recorder is done with it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn machine() -> Machine<crate::opcodes::OpcodeMaskParser> {
+        let mut machine = Machine::builder("TestVM").build().unwrap();
+        // LD I, 0x50; DRW V0, V0, 5; JP 0x204
+        machine
+            .load_rom_bytes(&[0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04])
+            .unwrap();
+        machine
+    }
+
+    #[test]
+    fn test_gif_time() {
+        assert_eq!(gif_time(0), 0);
+        assert_eq!(gif_time(1), 2);
+        assert_eq!(gif_time(3), 5);
+        assert_eq!(gif_time(60), 100);
+    }
+
+    #[test]
+    fn test_y4m() {
+        let output = Shared::default();
+        let options = ScreenshotOptions {
+            scale: 1,
+            ..ScreenshotOptions::default()
+        };
+        let mut recorder = Recorder::new(Box::new(output.clone()), RecordingFormat::Y4m, options);
+        let mut machine = machine();
+        for _ in 0..3 {
+            recorder.record(&machine).unwrap();
+            machine.run_frame().unwrap();
+        }
+        recorder.finish().unwrap();
+        let data = output.0.lock().unwrap();
+        let header = b"YUV4MPEG2 W64 H32 F60:1 Ip A1:1 C444 XCOLORRANGE=FULL\n";
+        assert!(data.starts_with(header));
+        let frame_size = b"FRAME\n".len() + 64 * 32 * 3;
+        assert_eq!(data.len(), header.len() + 3 * frame_size);
+        // the first frame is blank, the second has the glyph's top left pixel lit
+        assert_eq!(data[header.len() + 6], 0);
+        assert_eq!(data[header.len() + frame_size + 6], 255);
+    }
+
+    #[test]
+    fn test_gif() {
+        let output = Shared::default();
+        let mut recorder = Recorder::new(
+            Box::new(output.clone()),
+            RecordingFormat::Gif,
+            ScreenshotOptions::default(),
+        );
+        let mut machine = machine();
+        for _ in 0..61 {
+            recorder.record(&machine).unwrap();
+            machine.run_frame().unwrap();
+        }
+        assert_eq!(recorder.frames_recorded(), 61);
+        drop(recorder);
+
+        let data = output.0.lock().unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(&data[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (256, 128));
+        // a blank tick, then the glyph for the remaining 60
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, vec![2, 100]);
+    }
+
+    #[test]
+    fn test_gif_too_large() {
+        let options = ScreenshotOptions {
+            scale: 2048,
+            ..ScreenshotOptions::default()
+        };
+        let mut recorder =
+            Recorder::new(Box::new(Shared::default()), RecordingFormat::Gif, options);
+        let error = recorder.record(&machine()).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::GifTooLarge {
+                width: 131_072,
+                height: 65_536
+            }
+        ));
+        assert_eq!(recorder.frames_recorded(), 0);
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::error::{ErrorKind, Result};
+use crate::frame::{Frame, Palette};
+
+pub const DEFAULT_SCALE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Pbm, // black and white, dark pixels are black
+    Pgm, // 8-bit greyscale
+}
+
+impl ImageFormat {
+    // Picked from the file extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "pbm" => Ok(ImageFormat::Pbm),
+            "pgm" => Ok(ImageFormat::Pgm),
+            _ => Err(ErrorKind::UnknownImageFormat(path.display().to_string()).into()),
+        }
+    }
+}
+
+// How screenshots come out: every pixel becomes a `scale` x `scale` square.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenshotOptions {
+    pub scale: usize,
+    pub palette: Palette,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        Self {
+            scale: DEFAULT_SCALE,
+            palette: Palette::default(),
+        }
+    }
+}
+
+// Rec. 601 luma of a 0xRRGGBB colour.
+fn luma(rgb: u32) -> u8 {
+    let (r, g, b) = ((rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF);
+    ((r * 299 + g * 587 + b * 114) / 1000) as u8
+}
+
+pub fn write_image<W: Write>(
+    frame: &Frame,
+    scale_by: usize,
+    format: ImageFormat,
+    writer: W,
+) -> Result<()> {
+    let scale_by = scale_by.max(1);
+    let frame = frame.resized(frame.width * scale_by, frame.height * scale_by);
+    match format {
+        ImageFormat::Png => write_png(&frame, writer),
+        ImageFormat::Pbm => write_pbm(&frame, writer),
+        ImageFormat::Pgm => write_pgm(&frame, writer),
+    }
+}
+
+// Write an image file in the format its extension asks for.
+pub fn save<P: AsRef<Path>>(frame: &Frame, scale_by: usize, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let format = ImageFormat::from_path(path)?;
+    let writer = BufWriter::new(File::create(path)?);
+    write_image(frame, scale_by, format, writer)
+}
+
+fn write_png<W: Write>(frame: &Frame, writer: W) -> Result<()> {
+    #[allow(clippy::cast_possible_truncation)]
+    let mut encoder = png::Encoder::new(writer, frame.width as u32, frame.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut data = Vec::with_capacity(frame.pixels.len() * 3);
+    for rgb in frame.pixels.iter() {
+        data.extend_from_slice(&rgb.to_be_bytes()[1..]);
+    }
+    encoder
+        .write_header()
+        .and_then(|mut png| png.write_image_data(&data))
+        .map_err(io::Error::from)?;
+    Ok(())
+}
+
+// Binary PBM, rows are padded to whole bytes and 1 is black.
+fn write_pbm<W: Write>(frame: &Frame, mut writer: W) -> Result<()> {
+    write!(writer, "P4\n{} {}\n", frame.width, frame.height)?;
+    for row in frame.pixels.chunks(frame.width) {
+        let mut bytes = vec![0u8; frame.width.div_ceil(8)];
+        for (x, rgb) in row.iter().enumerate() {
+            if luma(*rgb) < 0x80 {
+                bytes[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        writer.write_all(&bytes)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_pgm<W: Write>(frame: &Frame, mut writer: W) -> Result<()> {
+    write!(writer, "P5\n{} {}\n255\n", frame.width, frame.height)?;
+    let data: Vec<u8> = frame.pixels.iter().map(|rgb| luma(*rgb)).collect();
+    writer.write_all(&data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkerboard() -> Frame {
+        Frame {
+            width: 2,
+            height: 2,
+            pixels: vec![0xFF_FFFF, 0x00_0000, 0x00_0000, 0xFF_FFFF],
+        }
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ImageFormat::from_path(Path::new("shot.PNG")).unwrap(),
+            ImageFormat::Png
+        );
+        assert_eq!(
+            ImageFormat::from_path(Path::new("shot.pbm")).unwrap(),
+            ImageFormat::Pbm
+        );
+        assert!(ImageFormat::from_path(Path::new("shot.jpg")).is_err());
+    }
+
+    #[test]
+    fn test_pbm() {
+        let mut out = Vec::new();
+        write_image(&checkerboard(), 1, ImageFormat::Pbm, &mut out).unwrap();
+        assert_eq!(out, b"P4\n2 2\n\x40\x80");
+
+        let mut out = Vec::new();
+        write_image(&checkerboard(), 8, ImageFormat::Pbm, &mut out).unwrap();
+        assert_eq!(&out[..9], b"P4\n16 16\n");
+        assert_eq!(out[9..11], [0x00, 0xFF]);
+        assert_eq!(out.len(), 9 + 16 * 2);
+    }
+
+    #[test]
+    fn test_pgm() {
+        let mut out = Vec::new();
+        write_image(&checkerboard(), 1, ImageFormat::Pgm, &mut out).unwrap();
+        assert_eq!(out, b"P5\n2 2\n255\n\xFF\x00\x00\xFF");
+    }
+
+    #[test]
+    fn test_png() {
+        let mut out = Vec::new();
+        write_image(&checkerboard(), 3, ImageFormat::Png, &mut out).unwrap();
+        let decoder = png::Decoder::new(&out[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (6, 6));
+        assert_eq!(data[..3], [0xFF, 0xFF, 0xFF]);
+        assert_eq!(data[9..12], [0x00, 0x00, 0x00]);
+    }
+}
@@ -0,0 +1,465 @@
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+use std::fmt::Write as _;
+use std::io::{self, Stdout, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::audio::{AudioSink, Buzzer, NullSink};
+use crate::core::Machine;
+use crate::error::Result;
+use crate::frame::{Frame, Palette, BLACK};
+use crate::instructions::InstructionParser;
+use crate::keypad::KEY_COUNT;
+use crate::recorder::Recorder;
+use crate::screenshot::ScreenshotOptions;
+use crate::timers::{FRAME_DURATION, TIMER_FREQUENCY};
+
+// The usual layout, the left hand side of a QWERTY keyboard mapped onto the
+// hex keypad:  1 2 3 C / 4 5 6 D / 7 8 9 E / A 0 B F
+pub const KEY_LAYOUT: [(char, u8); KEY_COUNT] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xC),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xD),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xE),
+    ('z', 0xA),
+    ('x', 0x0),
+    ('c', 0xB),
+    ('v', 0xF),
+];
+
+// Terminals without the keyboard enhancement protocol never report key
+// releases, so there a key counts as held until it hasn't been seen for this
+// long. It has to outlast the pause before auto-repeat starts, usually 250 to
+// 600 ms, or a held key would be released and pressed again, completing a
+// waiting Fx0A each time. After that, repeats come much faster.
+const KEY_HOLD: Duration = Duration::from_millis(650);
+// `+` stops speeding up here, six million instructions a second is already
+// more than a frame's worth of time allows.
+pub const MAX_CYCLES_PER_FRAME: u32 = 100_000;
+const UPPER_HALF_BLOCK: char = '\u{2580}';
+const HELP: &str = "Esc quit, Space pause, Tab step, +/- speed, Backspace reset, F9 record";
+
+// The speeds `+` and `-` step through, about a quarter faster or slower.
+fn faster(cycles: u32) -> u32 {
+    cycles
+        .saturating_add(cycles / 4 + 1)
+        .min(MAX_CYCLES_PER_FRAME)
+}
+
+fn slower(cycles: u32) -> u32 {
+    (cycles - cycles / 5).max(1)
+}
+
+// The hex key a keyboard key is mapped to, if any.
+pub fn keypad_key(key: char) -> Option<u8> {
+    let key = key.to_ascii_lowercase();
+    KEY_LAYOUT
+        .iter()
+        .find(|(mapped, _)| *mapped == key)
+        .map(|(_, hex)| *hex)
+}
+
+fn color(rgb: u32) -> Color {
+    Color::Rgb {
+        r: (rgb >> 16) as u8,
+        g: (rgb >> 8) as u8,
+        b: rgb as u8,
+    }
+}
+
+/**
+ * Draw a frame with Unicode half blocks, two pixels per character cell: the
+ * foreground colour is the upper pixel, the background colour the lower one.
+ * Colours are only emitted when they change.
+ */
+pub fn render(frame: &Frame) -> String {
+    let mut out = String::new();
+    for y in (0..frame.height).step_by(2) {
+        let mut current = None;
+        for x in 0..frame.width {
+            let upper = frame.pixel(x, y);
+            let lower = if y + 1 < frame.height {
+                frame.pixel(x, y + 1)
+            } else {
+                BLACK
+            };
+            if current != Some((upper, lower)) {
+                let _ = write!(
+                    out,
+                    "{}{}",
+                    SetForegroundColor(color(upper)),
+                    SetBackgroundColor(color(lower))
+                );
+                current = Some((upper, lower));
+            }
+            out.push(UPPER_HALF_BLOCK);
+        }
+        let _ = write!(out, "{}\r\n", ResetColor);
+    }
+    out
+}
+
+pub fn status_line<T: InstructionParser>(
+    machine: &Machine<T>,
+    paused: bool,
+    recording: bool,
+) -> String {
+    let state = if machine.is_halted() {
+        "halted"
+    } else if paused {
+        "paused"
+    } else {
+        "running"
+    };
+    format!(
+        "PC {:#06X}  {} ips  {}{}  [{}]",
+        machine.pc(),
+        u64::from(machine.cycles_per_frame()) * u64::from(TIMER_FREQUENCY),
+        state,
+        if recording { "  REC" } else { "" },
+        HELP
+    )
+}
+
+// Puts the terminal into raw mode on the alternate screen until dropped.
+struct RawTerminal {
+    enhanced_keys: bool,
+}
+
+impl RawTerminal {
+    fn enter(stdout: &mut Stdout) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        // Ask for key release events where the terminal can report them.
+        let enhanced_keys = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if enhanced_keys {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(Self { enhanced_keys })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.enhanced_keys {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/**
+ * Runs a machine in the terminal, which works fine over SSH.
+ * The machine is driven one 60 Hz frame at a time from the same thread that
+ * reads the keyboard and draws the screen. The ROM is kept around so a reset
+ * can load it again.
+*/
+pub struct TerminalFrontend<T: InstructionParser> {
+    machine: Machine<T>,
+    rom: Vec<u8>,
+    palette: Palette,
+    paused: bool,
+    held: [Option<Instant>; KEY_COUNT], // when each key was last seen
+    frames: u64,                        // frames run, not counting paused ones
+    screenshot: Option<(u64, PathBuf, ScreenshotOptions)>,
+    recorder: Option<Recorder>,
+    record_path: Option<PathBuf>, // where F9 records to, a new file each time if unset
+    record_options: ScreenshotOptions,
+    buzzer: Buzzer,
+    audio: Box<dyn AudioSink>, // there is no sound card output, only files
+}
+
+impl<T> TerminalFrontend<T>
+where
+    T: InstructionParser,
+{
+    pub fn new(machine: Machine<T>, rom: Vec<u8>) -> Self {
+        Self {
+            machine,
+            rom,
+            palette: Palette::default(),
+            paused: false,
+            held: [None; KEY_COUNT],
+            frames: 0,
+            screenshot: None,
+            recorder: None,
+            record_path: None,
+            record_options: ScreenshotOptions::default(),
+            buzzer: Buzzer::new(),
+            audio: Box::new(NullSink),
+        }
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    // Save a screenshot to `path` once `frame` frames have run.
+    pub fn screenshot_at(&mut self, frame: u64, path: PathBuf, options: ScreenshotOptions) {
+        self.screenshot = Some((frame, path, options));
+    }
+
+    // Start recording to `path` right away, F9 stops and restarts it.
+    pub fn record_to(&mut self, path: PathBuf, options: ScreenshotOptions) -> Result<()> {
+        self.recorder = Some(Recorder::create(&path, options)?);
+        self.record_path = Some(path);
+        self.record_options = options;
+        Ok(())
+    }
+
+    // Where the buzzer goes, e.g. a `WavSink`.
+    pub fn play_to(&mut self, sink: Box<dyn AudioSink>) {
+        self.audio = sink;
+    }
+
+    fn toggle_recording(&mut self) -> Result<()> {
+        match self.recorder.take() {
+            Some(mut recorder) => recorder.finish(),
+            None => {
+                let path = self.record_path.clone().unwrap_or_else(|| {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |time| time.as_secs());
+                    PathBuf::from(format!("chip8-{}.gif", now))
+                });
+                self.recorder = Some(Recorder::create(path, self.record_options)?);
+                Ok(())
+            }
+        }
+    }
+
+    // Returns when the user quits. A program that exits stays on screen.
+    pub fn run(&mut self) -> Result<()> {
+        let mut stdout = io::stdout();
+        let raw = RawTerminal::enter(&mut stdout)?;
+        let mut next_frame = Instant::now();
+        loop {
+            while event::poll(Duration::from_secs(0))? {
+                let quit = match event::read()? {
+                    Event::Key(key) => !self.handle_key(key, raw.enhanced_keys)?,
+                    Event::Resize(_, _) => {
+                        queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+                        false
+                    }
+                    _ => false,
+                };
+                if quit {
+                    return Ok(());
+                }
+            }
+            if !raw.enhanced_keys {
+                self.release_stale_keys(Instant::now());
+            }
+            if !self.paused {
+                self.machine.run_frame()?;
+                self.frames += 1;
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.record(&self.machine)?;
+                }
+                self.buzzer.play(&self.machine, self.audio.as_mut())?;
+            }
+            self.take_screenshot()?;
+            self.draw(&mut stdout)?;
+            next_frame += FRAME_DURATION;
+            let now = Instant::now();
+            if next_frame > now {
+                thread::sleep(next_frame - now);
+            } else {
+                next_frame = now;
+            }
+        }
+    }
+
+    // Returns false when the user asked to quit.
+    fn handle_key(&mut self, key: KeyEvent, enhanced_keys: bool) -> Result<bool> {
+        let released = key.kind == KeyEventKind::Release;
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(false)
+            }
+            KeyCode::Char(c) => {
+                if let Some(hex) = keypad_key(c) {
+                    if released {
+                        self.release(hex);
+                    } else {
+                        self.machine.press_key(hex);
+                        if !enhanced_keys {
+                            self.held[usize::from(hex)] = Some(Instant::now());
+                        }
+                    }
+                    return Ok(true);
+                }
+            }
+            _ => {}
+        }
+        if released {
+            return Ok(true);
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(false),
+            KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Tab if self.paused => self.machine.step()?,
+            KeyCode::Char('+') => {
+                let cycles = self.machine.cycles_per_frame();
+                self.machine.set_cycles_per_frame(faster(cycles));
+            }
+            KeyCode::Char('-') => {
+                let cycles = self.machine.cycles_per_frame();
+                self.machine.set_cycles_per_frame(slower(cycles));
+            }
+            KeyCode::F(9) => self.toggle_recording()?,
+            KeyCode::Backspace => {
+                self.machine.reset()?;
+                self.machine.load_rom_bytes(&self.rom)?;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn take_screenshot(&mut self) -> Result<()> {
+        match self.screenshot.take() {
+            Some((frame, path, options)) if frame <= self.frames => {
+                self.machine.save_screenshot(path, &options)
+            }
+            pending => {
+                self.screenshot = pending;
+                Ok(())
+            }
+        }
+    }
+
+    fn release(&mut self, hex: u8) {
+        self.held[usize::from(hex)] = None;
+        self.machine.release_key(hex);
+    }
+
+    // For terminals that don't report releases, see KEY_HOLD.
+    fn release_stale_keys(&mut self, now: Instant) {
+        for hex in 0..KEY_COUNT as u8 {
+            if let Some(seen) = self.held[usize::from(hex)] {
+                if now.saturating_duration_since(seen) >= KEY_HOLD {
+                    self.release(hex);
+                }
+            }
+        }
+    }
+
+    fn draw(&self, stdout: &mut Stdout) -> io::Result<()> {
+        let frame = Frame::capture(&self.machine, &self.palette);
+        queue!(stdout, cursor::MoveTo(0, 0))?;
+        write!(stdout, "{}", render(&frame))?;
+        queue!(stdout, terminal::Clear(terminal::ClearType::CurrentLine))?;
+        write!(
+            stdout,
+            "{}",
+            status_line(&self.machine, self.paused, self.recorder.is_some())
+        )?;
+        stdout.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypad_key() {
+        assert_eq!(keypad_key('1'), Some(0x1));
+        assert_eq!(keypad_key('4'), Some(0xC));
+        assert_eq!(keypad_key('X'), Some(0x0));
+        assert_eq!(keypad_key('v'), Some(0xF));
+        assert_eq!(keypad_key('p'), None);
+        let mut keys: Vec<u8> = KEY_LAYOUT.iter().map(|(_, hex)| *hex).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_key_held_until_first_repeat() {
+        let mut machine = Machine::builder("TestVM").build().unwrap();
+        // LD V0, K
+        machine.load_rom_bytes(&[0xF0, 0x0A]).unwrap();
+        machine.step().unwrap();
+        let mut frontend = TerminalFrontend::new(machine, Vec::new());
+        let press = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE);
+        assert!(frontend.handle_key(press, false).unwrap());
+        let seen = frontend.held[0x5].unwrap();
+
+        // a slow first auto-repeat arrives 600 ms in, the key is still down
+        frontend.release_stale_keys(seen + Duration::from_millis(600));
+        assert!(frontend.machine.keypad().is_pressed(0x5));
+        assert_eq!(frontend.machine.registers()[0], 0);
+
+        // once it is let go, Fx0A gets the key exactly once
+        frontend.release_stale_keys(seen + KEY_HOLD);
+        assert!(!frontend.machine.keypad().is_pressed(0x5));
+        assert_eq!(frontend.machine.registers()[0], 0x5);
+    }
+
+    #[test]
+    fn test_render() {
+        let frame = Frame {
+            width: 2,
+            height: 2,
+            pixels: vec![0xFF_FFFF, 0xFF_FFFF, 0x00_0000, 0xFF_FFFF],
+        };
+        let white = Color::Rgb {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let black = Color::Rgb { r: 0, g: 0, b: 0 };
+        let expected = format!(
+            "{}{}\u{2580}{}{}\u{2580}{}\r\n",
+            SetForegroundColor(white),
+            SetBackgroundColor(black),
+            SetForegroundColor(white),
+            SetBackgroundColor(white),
+            ResetColor
+        );
+        assert_eq!(render(&frame), expected);
+    }
+
+    #[test]
+    fn test_status_line() {
+        let machine = Machine::builder("TestVM").build().unwrap();
+        let status = status_line(&machine, true, false);
+        assert!(status.starts_with("PC 0x0200  600 ips  paused  ["));
+        let status = status_line(&machine, false, true);
+        assert!(status.starts_with("PC 0x0200  600 ips  running  REC  ["));
+
+        let mut machine = machine;
+        machine.set_cycles_per_frame(u32::MAX);
+        let status = status_line(&machine, false, false);
+        assert!(status.starts_with("PC 0x0200  257698037700 ips"));
+    }
+
+    #[test]
+    fn test_speed() {
+        assert_eq!(faster(10), 13);
+        assert_eq!(slower(10), 8);
+        assert_eq!(slower(1), 1);
+        assert_eq!(faster(MAX_CYCLES_PER_FRAME - 1), MAX_CYCLES_PER_FRAME);
+        assert_eq!(faster(u32::MAX), MAX_CYCLES_PER_FRAME);
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+pub const TIMER_FREQUENCY: u32 = 60;
+pub const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / TIMER_FREQUENCY as u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEdge {
+    Started,
+    Stopped,
+}
+
+/**
+ * The delay and sound timers.
+ * Both count down by one on every tick until they reach zero. Ticking is
+ * driven by the machine once per 60 Hz frame, independently of how many
+ * instructions ran during that frame.
+*/
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Timers {
+    delay: u8,
+    sound: u8,
+    sound_was_active: bool, // sound state as of the last call to sound_edge
+    buzzed: bool,           // whether the buzzer was on during the last tick
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn delay(&self) -> u8 {
+        self.delay
+    }
+
+    pub fn sound(&self) -> u8 {
+        self.sound
+    }
+
+    pub fn set_delay(&mut self, value: u8) {
+        self.delay = value;
+    }
+
+    pub fn set_sound(&mut self, value: u8) {
+        self.sound = value;
+    }
+
+    // The buzzer sounds for as long as the sound timer is non-zero.
+    pub fn sound_active(&self) -> bool {
+        self.sound > 0
+    }
+
+    // Whether the frame ended by the last tick should be heard, so a sound
+    // timer set to N sounds for N whole frames.
+    pub fn buzzed(&self) -> bool {
+        self.buzzed
+    }
+
+    pub fn tick(&mut self) {
+        self.buzzed = self.sound_active();
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+
+    // Report whether the buzzer turned on or off since the previous call.
+    pub fn sound_edge(&mut self) -> Option<SoundEdge> {
+        let active = self.sound_active();
+        if active == self.sound_was_active {
+            return None;
+        }
+        self.sound_was_active = active;
+        if active {
+            Some(SoundEdge::Started)
+        } else {
+            Some(SoundEdge::Stopped)
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick() {
+        let mut timers = Timers::new();
+        timers.set_delay(2);
+        timers.set_sound(1);
+        timers.tick();
+        assert_eq!(timers.delay(), 1);
+        assert_eq!(timers.sound(), 0);
+        assert!(timers.buzzed());
+        timers.tick();
+        assert!(!timers.buzzed());
+        timers.tick();
+        // timers stop at zero instead of wrapping around
+        assert_eq!(timers.delay(), 0);
+        assert_eq!(timers.sound(), 0);
+    }
+
+    #[test]
+    fn test_sound_edge() {
+        let mut timers = Timers::new();
+        assert_eq!(timers.sound_edge(), None);
+        timers.set_sound(2);
+        assert_eq!(timers.sound_edge(), Some(SoundEdge::Started));
+        assert_eq!(timers.sound_edge(), None);
+        timers.tick();
+        assert_eq!(timers.sound_edge(), None);
+        timers.tick();
+        assert_eq!(timers.sound_edge(), Some(SoundEdge::Stopped));
+        assert_eq!(timers.sound_edge(), None);
+    }
+}